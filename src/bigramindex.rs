@@ -1,8 +1,11 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{hash_map, HashMap, HashSet};
 
-use super::types::{PairChar, PairString};
-use super::wordstore::WordStore;
+use super::template::BlankPattern;
+use super::types::{PairChar, PairString, WordList};
+use super::wordstore::{LineMode, WordStore};
+use rayon::prelude::*;
 
+#[derive(Debug, PartialEq)]
 pub struct BigramIndexTree {
     index: BigramIndexTreeInner,
     depth: usize,
@@ -21,6 +24,7 @@ impl BigramIndexTree {
         self.index.insert(key, Some(Box::new(new_leaf)));
     }
 
+    // dump every line in the index for debugging, to stderr so it stays out of the solution
     pub fn print(&self, prefix: &str) {
         for (key, leaf) in &self.index {
             let word = format!("{}-{}", prefix, &key.decode());
@@ -28,13 +32,67 @@ impl BigramIndexTree {
                 Some(l) => {
                     l.print(&word);
                 }
-                None => println!("{}", word),
+                None => eprintln!("{}", word),
             }
         }
     }
 
     // populate the index based on a series of pairwords
     pub fn build(size: usize, word_store: &WordStore, max_blanks: usize) -> BigramIndexTree {
+        BigramIndexTree::build_from_words(size, word_store.permuted_words_by_length(size, max_blanks))
+    }
+
+    // populate the index with lines laid out according to the line mode
+    pub fn build_lines(size: usize, word_store: &WordStore, max_blanks: usize, line_mode: LineMode) -> BigramIndexTree {
+        BigramIndexTree::build_from_words(size, word_store.lines_by_length(size, max_blanks, line_mode))
+    }
+
+    // populate the index with only the lines which fit one of a template's blank layouts
+    pub fn build_for_patterns(
+        size: usize,
+        word_store: &WordStore,
+        patterns: &HashSet<BlankPattern>,
+        line_mode: LineMode,
+    ) -> BigramIndexTree {
+        BigramIndexTree::build_from_words(size, word_store.patterned_words_by_length(size, patterns, line_mode))
+    }
+
+    // populate the index from lines which are all of the given size, in parallel
+    pub fn build_from_words(size: usize, words: WordList) -> BigramIndexTree {
+        // partition the words on their first pairchar
+        let mut partitions: HashMap<PairChar, Vec<PairString>> = HashMap::new();
+        for word in words {
+            partitions.entry(word[0]).or_default().push(word);
+        }
+
+        let subtrees: Vec<(PairChar, Option<Box<BigramIndexTree>>)> = partitions
+            .into_par_iter()
+            .map(|(key_char, words)| {
+                if size == 1 {
+                    return (key_char, None);
+                }
+
+                let mut subtree = BigramIndexTree::new(1);
+                for word in words {
+                    BigramIndexTree::index_word(&mut subtree, &word.slice()[1..]);
+                }
+                subtree.index.shrink_to_fit();
+
+                (key_char, Some(Box::new(subtree)))
+            })
+            .collect();
+
+        let mut root = BigramIndexTree::new(0);
+        root.index.extend(subtrees);
+
+        // return the populated index
+        root
+    }
+
+    // single-threaded equivalent of build, inserting each word in turn from the root, to check
+    // the parallel build against
+    #[cfg(test)]
+    fn build_serial(size: usize, word_store: &WordStore, max_blanks: usize) -> BigramIndexTree {
         let mut root = BigramIndexTree::new(0);
 
         for word in word_store.permuted_words_by_length(size, max_blanks) {
@@ -45,6 +103,125 @@ impl BigramIndexTree {
         root
     }
 
+    // estimate the size of the index that build would produce, without permuting any words
+    pub fn estimate(size: usize, word_store: &WordStore, max_blanks: usize) -> IndexEstimate {
+        let padded_sizes = WordStore::blank_padded_sizes(size, max_blanks);
+
+        let mut permuted_words = 0;
+        for (word_size, blanks) in &padded_sizes {
+            permuted_words += word_store.words_by_length(*word_size).len() * binomial(size, *blanks);
+        }
+
+        // every distinct stem of a permuted word is an entry in some node of the tree: a stem of
+        // length d holding j blanks is one of C(d, j) blank layouts around a (d - j) pairchar
+        // prefix of any word with room for at least j blanks
+        let mut entries = 0;
+        let mut nodes = 1;
+        for stem_length in 1..=size {
+            for stem_blanks in 0..=stem_length {
+                let prefix_length = stem_length - stem_blanks;
+                let mut prefixes: HashSet<&[PairChar]> = HashSet::new();
+                for (word_size, blanks) in &padded_sizes {
+                    if *blanks < stem_blanks || *word_size < prefix_length {
+                        continue;
+                    }
+                    for word in word_store.words_by_length(*word_size) {
+                        prefixes.insert(word.slice_to(prefix_length));
+                    }
+                }
+
+                let stems = binomial(stem_length, stem_blanks) * prefixes.len();
+                entries += stems;
+                if stem_length < size {
+                    nodes += stems;
+                }
+            }
+        }
+
+        IndexEstimate {
+            size,
+            permuted_words,
+            nodes,
+            entries,
+        }
+    }
+
+    // number of nodes in the tree, including this one
+    pub fn node_count(&self) -> usize {
+        1 + self
+            .index
+            .values()
+            .map(|x| x.as_ref().map_or(0, |y| y.node_count()))
+            .sum::<usize>()
+    }
+
+    // number of keys held across all of the nodes in the tree
+    pub fn entry_count(&self) -> usize {
+        self.index.len()
+            + self
+                .index
+                .values()
+                .map(|x| x.as_ref().map_or(0, |y| y.entry_count()))
+                .sum::<usize>()
+    }
+
+    // add a word, with all of its blank permutations, to an index of lines of the given size,
+    // returning whether the word fits in the index at all
+    pub fn insert_word(&mut self, word: &PairString, size: usize, max_blanks: usize) -> bool {
+        let permutations = WordStore::permute_to_fill(word, size, max_blanks);
+        for permutation in &permutations {
+            BigramIndexTree::index_word(self, permutation.slice());
+        }
+
+        !permutations.is_empty()
+    }
+
+    // remove a word, with all of its blank permutations, pruning any branches of the tree which
+    // are left empty. Returns whether anything was removed
+    pub fn remove_word(&mut self, word: &PairString, size: usize, max_blanks: usize) -> bool {
+        let mut removed = false;
+        for permutation in WordStore::permute_to_fill(word, size, max_blanks) {
+            removed |= BigramIndexTree::unindex_word(self, permutation.slice());
+        }
+
+        removed
+    }
+
+    // check whether a complete line is held in the index
+    pub fn contains(&self, pair_slice: &[PairChar]) -> bool {
+        match pair_slice.split_first() {
+            None => false,
+            Some((key_char, [])) => matches!(self.index.get(key_char), Some(None)),
+            Some((key_char, remaining_slice)) => match self.index.get(key_char) {
+                Some(Some(child_node)) => child_node.contains(remaining_slice),
+                _ => false,
+            },
+        }
+    }
+
+    // recursive function to remove a line from the index tree, as used by remove_word
+    fn unindex_word(node: &mut BigramIndexTree, pair_slice: &[PairChar]) -> bool {
+        let key_char = &pair_slice[0];
+
+        if pair_slice.len() == 1 {
+            return node.index.remove(key_char).is_some();
+        }
+
+        let child_node = match node.index.get_mut(key_char) {
+            Some(Some(v)) => v,
+            _ => return false,
+        };
+
+        let removed = BigramIndexTree::unindex_word(child_node, &pair_slice[1..]);
+
+        // prune the branch if that was the last word running through it
+        if child_node.index.is_empty() {
+            node.index.remove(key_char);
+        }
+
+        removed
+    }
+
     // recursive function to create the index tree, as used by build
     fn index_word(node: &mut BigramIndexTree, pair_slice: &[PairChar]) {
         let key_char = &pair_slice[0];
@@ -65,7 +242,7 @@ impl BigramIndexTree {
                 // unbox the index to add to it, in order to avoid interior mutability woes
                 // TODO: Find a way to use the mutable ref from get_mut directly...
                 let mut child_node: BigramIndexTree =
-                    *(node.index.get_mut(&key_char).unwrap().take().unwrap());
+                    *(node.index.get_mut(key_char).unwrap().take().unwrap());
 
                 BigramIndexTree::index_word(&mut child_node, remaining_slice);
 
@@ -84,10 +261,7 @@ impl BigramIndexTree {
         let mut possible_chars: Vec<HashSet<PairChar>> = Vec::new();
 
         for stem in stems {
-            let new_possibles = match BigramIndexTree::next_possible_pairchars(self, stem) {
-                Some(v) => v,
-                None => HashSet::new(),
-            };
+            let new_possibles = BigramIndexTree::next_possible_pairchars(self, stem).unwrap_or_default();
             possible_chars.push(new_possibles);
         }
 
@@ -107,13 +281,9 @@ impl BigramIndexTree {
         }
         let key_char = &stem[node.depth];
 
-        if node.index.get(&key_char).is_none() {
-            // we've hit the end of the indexchain before we've run out of word
-            // meaning there are no subsequent next_possible_pairchars
-            return None;
-        }
-
-        let next_index_ref = match node.index.get(&key_char).expect("Key Not Found").as_ref() {
+        // if we hit the end of the indexchain before we've run out of word there are no
+        // subsequent next_possible_pairchars
+        let next_index_ref = match node.index.get(key_char)?.as_ref() {
             None => {
                 panic!("We've hit a none in the index at depth {}", node.depth);
             }
@@ -126,129 +296,223 @@ impl BigramIndexTree {
             Some(next_index_ref.get_keys_as_hashset())
         } else {
             // we've got more stem to descend down...
-            BigramIndexTree::next_possible_pairchars(&next_index_ref, stem)
+            BigramIndexTree::next_possible_pairchars(next_index_ref, stem)
         }
     }
 
     pub fn get_keys_as_hashset(&self) -> HashSet<PairChar> {
         let mut key_set: HashSet<PairChar> = HashSet::new();
         for key in self.index.keys() {
-            key_set.insert(key.clone());
+            key_set.insert(*key);
         }
         key_set
     }
 
-    pub fn get_candidate_words(
-        root_index_node: &BigramIndexTree,
-        filters: &[HashSet<PairChar>],
-    ) -> Option<Vec<PairString>> {
-        // extract the possible words derived from a BigramIndexTree and a set of filters for each
-        // depth
+    // create a cursor positioned at the root of this index
+    pub fn cursor(&self) -> IndexCursor<'_> {
+        IndexCursor::new(self)
+    }
 
-        // first make sure that all of our filters can match something, immediately return None
-        // otherwise
-        for filter in filters {
-            if filter.is_empty() {
-                return None;
-            }
+    // lazily walk the index, yielding only those words which match the set of filters at each
+    // depth (one filter per pairchar of the word)
+    pub fn get_candidate_words<'a>(
+        root_index_node: &'a BigramIndexTree,
+        filters: &'a [HashSet<PairChar>],
+    ) -> CandidateWords<'a> {
+        CandidateWords::new(root_index_node, filters)
+    }
+}
+
+// Indices for each of the line lengths used by a puzzle, so that all of the lines of the same
+// length (whichever axis they're on) share a single index
+pub struct IndexCache {
+    indices: HashMap<usize, BigramIndexTree>,
+    max_blanks: usize,
+    line_mode: LineMode,
+}
+
+impl IndexCache {
+    pub fn new(max_blanks: usize, line_mode: LineMode) -> IndexCache {
+        IndexCache {
+            indices: HashMap::new(),
+            max_blanks,
+            line_mode,
         }
+    }
 
-        // descend the index tree with the set filters to find possible matches
-        let reverse_words =
-            match BigramIndexTree::get_reversed_candidate_words(root_index_node, filters, 0) {
-                Some(v) => v,
-                None => {
-                    return None;
-                }
-            };
+    // build the index for a line length if it's not already in the cache
+    pub fn get_or_build(&mut self, size: usize, word_store: &WordStore) -> &BigramIndexTree {
+        let max_blanks = self.max_blanks;
+        let line_mode = self.line_mode;
+
+        self.indices
+            .entry(size)
+            .or_insert_with(|| BigramIndexTree::build_lines(size, word_store, max_blanks, line_mode))
+    }
+
+    pub fn get(&self, size: usize) -> Option<&BigramIndexTree> {
+        self.indices.get(&size)
+    }
+
+    pub fn line_mode(&self) -> LineMode {
+        self.line_mode
+    }
+}
 
-        let mut forward_words: Vec<PairString> = Vec::new();
-        for word in reverse_words {
-            let mut new_word = word.clone();
-            new_word.reverse();
-            forward_words.push(new_word);
+// approximate heap usage of the parts of an index, including the hashbrown control bytes and
+// spare capacity left in each HashMap
+const NODE_BYTES: usize = 64;
+const ENTRY_BYTES: usize = 32;
+
+// The expected size of a BigramIndexTree, as calculated by BigramIndexTree::estimate
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct IndexEstimate {
+    pub size: usize,
+    pub permuted_words: usize,
+    pub nodes: usize,
+    pub entries: usize,
+}
+
+impl IndexEstimate {
+    // peak memory during build: the tree plus the list of permuted words it's built from
+    pub fn bytes(&self) -> usize {
+        let word_bytes = std::mem::size_of::<PairString>() + self.size * std::mem::size_of::<PairChar>();
+
+        self.nodes * NODE_BYTES + self.entries * ENTRY_BYTES + self.permuted_words * word_bytes
+    }
+}
+
+fn binomial(n: usize, k: usize) -> usize {
+    (0..k).fold(1, |acc, i| acc * (n - i) / (i + 1))
+}
+
+// A handle on a position within a BigramIndexTree, so a partially built line can step one
+// pairchar deeper (or back) without re-descending the tree from the root each time.
+// Stepping onto a pairchar which isn't in the tree is allowed, after which there are no
+// possible next pairchars until the cursor is stepped back.
+#[derive(Clone, Debug)]
+pub struct IndexCursor<'a> {
+    path: Vec<Option<&'a BigramIndexTree>>,
+}
+
+impl<'a> IndexCursor<'a> {
+    pub fn new(root_index_node: &'a BigramIndexTree) -> IndexCursor<'a> {
+        IndexCursor {
+            path: vec![Some(root_index_node)],
         }
+    }
 
-        Some(forward_words)
+    // number of pairchars the cursor has descended through
+    pub fn depth(&self) -> usize {
+        self.path.len() - 1
     }
 
-    fn get_reversed_candidate_words(
-        index_node: &BigramIndexTree,
-        filters: &[HashSet<PairChar>],
-        depth: usize,
-    ) -> Option<Vec<PairString>> {
-        // check that all of the filter sets have some characters at least,
-        // return early if any are None
-        let filters_length = filters.len();
-        if index_node.depth >= filters_length {
-            panic!(
-                "Filters length does not match horizontal index depth: {} vs {}",
-                filters_length, index_node.depth
-            );
+    // step one pairchar deeper, returning whether the new position is still within the tree
+    pub fn descend(&mut self, pair_char: PairChar) -> bool {
+        let next_node = match self.path.last() {
+            Some(Some(node)) => node.index.get(&pair_char).and_then(|x| x.as_deref()),
+            _ => None,
+        };
+        self.path.push(next_node);
+
+        next_node.is_some()
+    }
+
+    // step back up to the previous pairchar, the root is never removed
+    pub fn ascend(&mut self) {
+        if self.path.len() > 1 {
+            self.path.pop();
         }
+    }
 
-        let mut reversed_words: Vec<PairString> = Vec::new();
-        let intersection =
-            BigramIndexTree::pairchar_intersection(index_node, &filters[index_node.depth]);
+    // the set of pairchars which can follow the current position
+    pub fn possible_pairchars(&self) -> HashSet<PairChar> {
+        match self.path.last() {
+            Some(Some(node)) => node.get_keys_as_hashset(),
+            _ => HashSet::new(),
+        }
+    }
+}
 
-        if intersection.is_empty() {
-            // no available matches at this depth, meaning we've not got any matches for this
-            // branch
-            return None;
+// Iterator over the words in a BigramIndexTree which pass a set of per-depth filters.
+// The tree is walked depth-first with one HashMap iterator per level, so candidates are only
+// generated as they are requested.
+pub struct CandidateWords<'a> {
+    filters: &'a [HashSet<PairChar>],
+    stack: Vec<IndexIter<'a>>,
+    stem: PairString,
+}
+type IndexIter<'a> = hash_map::Iter<'a, PairChar, Option<Box<BigramIndexTree>>>;
+
+impl<'a> CandidateWords<'a> {
+    fn new(root_index_node: &'a BigramIndexTree, filters: &'a [HashSet<PairChar>]) -> CandidateWords<'a> {
+        // make sure that all of our filters can match something, otherwise there's no point in
+        // descending at all
+        let stack = if filters.is_empty() || filters.iter().any(|filter| filter.is_empty()) {
+            Vec::new()
+        } else {
+            vec![root_index_node.index.iter()]
+        };
+
+        CandidateWords {
+            filters,
+            stack,
+            stem: PairString::new(),
         }
+    }
+}
 
-        if index_node.depth == (filters_length - 1) {
-            // we've hit the last node in the index
+impl<'a> Iterator for CandidateWords<'a> {
+    type Item = PairString;
 
-            for pairchar in intersection {
-                let mut initial_word: PairString = PairString::new();
-                initial_word.push(pairchar);
+    fn next(&mut self) -> Option<PairString> {
+        let filters = self.filters;
 
-                reversed_words.push(initial_word);
-            }
-        } else {
-            // we're at an intermediate layer, so recurse down
-            // for pairchar in intersection {
-            for key_char in intersection {
-                let next_index_ref = index_node.index[&key_char].as_ref().unwrap();
-                let partial_words = match BigramIndexTree::get_reversed_candidate_words(
-                    &next_index_ref,
-                    filters,
-                    depth + 1,
-                ) {
-                    Some(v) => v,
-                    None => {
-                        continue;
+        while let Some(level) = self.stack.last_mut() {
+            let depth = self.stem.len();
+            match level.find(|(key, _)| filters[depth].contains(key)) {
+                None => {
+                    // this level is exhausted, so step back up the tree
+                    self.stack.pop();
+                    self.stem.pair_string.pop();
+                }
+                Some((key, child)) => {
+                    if depth == filters.len() - 1 {
+                        let mut word = self.stem.clone();
+                        word.push(*key);
+                        return Some(word);
                     }
-                };
 
-                for word in partial_words {
-                    let mut new_word: PairString = word.clone();
-                    new_word.push(key_char);
-                    reversed_words.push(new_word);
+                    let child = child.as_ref().unwrap_or_else(|| {
+                        panic!(
+                            "Filters length does not match index depth: {} vs {}",
+                            filters.len(),
+                            depth + 1
+                        )
+                    });
+                    self.stem.push(*key);
+                    self.stack.push(child.index.iter());
                 }
             }
         }
 
-        if !reversed_words.is_empty() {
-            Some(reversed_words)
-        } else {
-            None
-        }
+        None
     }
+}
 
-    fn pairchar_intersection(
-        index_node: &BigramIndexTree,
-        filter_set: &HashSet<PairChar>,
-    ) -> Vec<PairChar> {
-        // return a vec of PairChars which match both the index and the filter_set
-        let mut return_set: Vec<PairChar> = Vec::new();
-        for key in index_node.index.keys() {
-            if filter_set.contains(key) {
-                return_set.push(*key);
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use super::BigramIndexTree;
 
-        return_set
+    #[test]
+    fn parallel_build_matches_serial() {
+        let word_store = crate::generate_wordstore("tests/words-good-spaces-4x4");
+
+        for max_blanks in 0..=2 {
+            assert_eq!(
+                BigramIndexTree::build(4, &word_store, max_blanks),
+                BigramIndexTree::build_serial(4, &word_store, max_blanks)
+            );
+        }
     }
 }
@@ -0,0 +1,37 @@
+use super::puzzlegrid::{Entry, PuzzleGrid};
+use std::collections::HashMap;
+
+// read a clue database of word<TAB>clue lines into a map from word to clue, the words are
+// lowercased to match the grid tiles, and blank lines are skipped
+pub fn read_clues(clue_text: &str) -> Result<HashMap<String, String>, String> {
+    let mut clues: HashMap<String, String> = HashMap::new();
+
+    for (line_number, line) in clue_text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match line.split_once('\t') {
+            Some((word, clue)) if !word.trim().is_empty() => {
+                clues.insert(word.trim().to_lowercase(), clue.trim().to_string());
+            }
+            _ => return Err(format!("Expected word<TAB>clue on line {}", line_number + 1)),
+        }
+    }
+
+    Ok(clues)
+}
+
+// the clue for an entry, looked up by the word placed along it, shared by every output which
+// carries clues
+pub fn clue_for<'a>(entry: &Entry, clues: Option<&'a HashMap<String, String>>) -> Option<&'a str> {
+    clues?.get(&entry.word.decode()).map(String::as_str)
+}
+
+// the entries of a grid which have no clue, so editors know what still needs writing
+pub fn missing_clues(grid: &PuzzleGrid, clues: &HashMap<String, String>) -> Vec<Entry> {
+    grid.entries()
+        .into_iter()
+        .filter(|x| clue_for(x, Some(clues)).is_none())
+        .collect()
+}
@@ -0,0 +1,291 @@
+use super::bigramindex::BigramIndexTree;
+use super::puzzlegrid::{Direction, PuzzleGrid};
+use super::types::{PairChar, PairString};
+use std::collections::HashSet;
+use std::sync::atomic::AtomicBool;
+
+// how many candidate words are looked at per line when finding the dead cells, so that a
+// redraw stays quick with blanks allowed on a big dictionary
+const VIABLE_WORD_LIMIT: usize = 10_000;
+
+// A grid being built up by hand, a word at a time, with the indices used to suggest words
+// for the lines still open and to fill in the rest
+// every change keeps the previous tiles so it can be undone (and redone)
+pub struct GridEditor<'a> {
+    rows: Vec<Vec<PairChar>>,
+    undo_stack: Vec<Vec<Vec<PairChar>>>,
+    redo_stack: Vec<Vec<Vec<PairChar>>>,
+    horizontal_index: &'a BigramIndexTree,
+    vertical_index: &'a BigramIndexTree,
+}
+
+impl<'a> GridEditor<'a> {
+    // an empty grid, every tile a wildcard
+    pub fn new(
+        width: usize,
+        depth: usize,
+        horizontal_index: &'a BigramIndexTree,
+        vertical_index: &'a BigramIndexTree,
+    ) -> Result<GridEditor<'a>, String> {
+        if width == 0 || depth == 0 {
+            return Err("The grid needs a width and depth of at least 1".to_string());
+        }
+
+        Ok(GridEditor {
+            rows: vec![vec![PairChar::encode(b'?', b'?'); width]; depth],
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            horizontal_index,
+            vertical_index,
+        })
+    }
+
+    // carry on editing a grid read in with PuzzleGrid::parse
+    pub fn from_grid(
+        grid: &PuzzleGrid,
+        horizontal_index: &'a BigramIndexTree,
+        vertical_index: &'a BigramIndexTree,
+    ) -> Result<GridEditor<'a>, String> {
+        let mut editor = GridEditor::new(grid.width(), grid.depth(), horizontal_index, vertical_index)?;
+        editor.rows = grid_tiles(grid);
+        Ok(editor)
+    }
+
+    pub fn width(&self) -> usize {
+        self.rows[0].len()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn get_tile(&self, x: usize, y: usize) -> PairChar {
+        self.rows[y][x]
+    }
+
+    // the tiles placed so far, with the open tiles left as wildcards
+    pub fn grid(&self) -> PuzzleGrid {
+        PuzzleGrid::from_tiles(&self.rows).unwrap()
+    }
+
+    // the number of lines in a direction, and the length of each of them
+    fn line_count(&self, direction: Direction) -> usize {
+        match direction {
+            Direction::Across => self.depth(),
+            Direction::Down => self.width(),
+        }
+    }
+
+    fn line_length(&self, direction: Direction) -> usize {
+        match direction {
+            Direction::Across => self.width(),
+            Direction::Down => self.depth(),
+        }
+    }
+
+    // the tiles of a row (across) or column (down)
+    pub fn line(&self, direction: Direction, index: usize) -> Vec<PairChar> {
+        match direction {
+            Direction::Across => self.rows[index].clone(),
+            Direction::Down => self.rows.iter().map(|x| x[index]).collect(),
+        }
+    }
+
+    pub fn is_open(&self, direction: Direction, index: usize) -> bool {
+        self.line(direction, index).iter().any(PairChar::is_wildcard)
+    }
+
+    // write a word into a row or column, given as pairs of letters with "__" for a blank
+    // and "??" to leave a tile open
+    pub fn place_word(&mut self, direction: Direction, index: usize, word: &str) -> Result<(), String> {
+        if index >= self.line_count(direction) {
+            return Err(format!("There is no {} line {}", direction, index));
+        }
+        let length = self.line_length(direction);
+        if word.len() != 2 * length {
+            return Err(format!("Expected {} tiles in '{}'", length, word));
+        }
+        let tiles = (0..length)
+            .map(|i| {
+                word.get((2 * i)..(2 * i + 2))
+                    .map_or(Err(format!("Invalid tile in '{}'", word)), PairChar::parse)
+            })
+            .collect::<Result<Vec<PairChar>, String>>()?;
+
+        self.save_state();
+        for (i, tile) in tiles.into_iter().enumerate() {
+            match direction {
+                Direction::Across => self.rows[index][i] = tile,
+                Direction::Down => self.rows[i][index] = tile,
+            }
+        }
+        Ok(())
+    }
+
+    // write a single tile, a wildcard opening it back up
+    pub fn set_tile(&mut self, x: usize, y: usize, tile: PairChar) -> Result<(), String> {
+        if x >= self.width() || y >= self.depth() {
+            return Err(format!("There is no tile {} {}", x, y));
+        }
+
+        self.save_state();
+        self.rows[y][x] = tile;
+        Ok(())
+    }
+
+    // open a line back up, leaving every tile as a wildcard
+    pub fn clear_line(&mut self, direction: Direction, index: usize) -> Result<(), String> {
+        let open_word = "??".repeat(self.line_length(direction));
+        self.place_word(direction, index, &open_word)
+    }
+
+    fn save_state(&mut self) {
+        self.undo_stack.push(self.rows.clone());
+        self.redo_stack.clear();
+    }
+
+    // step back to the tiles before the last change, false if there's nothing to undo
+    pub fn undo(&mut self) -> bool {
+        match self.undo_stack.pop() {
+            Some(v) => {
+                self.redo_stack.push(std::mem::replace(&mut self.rows, v));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn redo(&mut self) -> bool {
+        match self.redo_stack.pop() {
+            Some(v) => {
+                self.undo_stack.push(std::mem::replace(&mut self.rows, v));
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn index(&self, direction: Direction) -> &'a BigramIndexTree {
+        match direction {
+            Direction::Across => self.horizontal_index,
+            Direction::Down => self.vertical_index,
+        }
+    }
+
+    // the tiles which could go in each position of a line: placed tiles stay as they are, and
+    // an open tile can be anything which follows on from the crossing line's tiles above (or
+    // to the left of) it, when they've all been placed
+    fn line_filters(&self, direction: Direction, index: usize) -> Vec<HashSet<PairChar>> {
+        let crossing = match direction {
+            Direction::Across => Direction::Down,
+            Direction::Down => Direction::Across,
+        };
+        let crossing_index = self.index(crossing);
+
+        self.line(direction, index)
+            .iter()
+            .enumerate()
+            .map(|(i, tile)| {
+                if !tile.is_wildcard() {
+                    return std::iter::once(*tile).collect();
+                }
+
+                let stem = &self.line(crossing, i)[..index];
+                if stem.is_empty() {
+                    crossing_index.get_keys_as_hashset()
+                } else if stem.iter().any(PairChar::is_wildcard) {
+                    PairChar::all_tiles()
+                } else {
+                    crossing_index.get_possible_pairchars(vec![stem]).remove(0)
+                }
+            })
+            .collect()
+    }
+
+    // words from the index which fit a line, given its placed tiles and the lines crossing it
+    pub fn suggestions(&self, direction: Direction, index: usize, limit: usize) -> Vec<PairString> {
+        let filters = self.line_filters(direction, index);
+        BigramIndexTree::get_candidate_words(self.index(direction), &filters)
+            .take(limit)
+            .collect()
+    }
+
+    // the tiles used by any of a line's suggestions, position by position
+    // the walk stops once every position has seen all the tiles it could take, and gives up
+    // after VIABLE_WORD_LIMIT words, counting every tile as viable rather than marking cells dead
+    // which might not be
+    fn viable_line_tiles(&self, direction: Direction, index: usize) -> Vec<HashSet<PairChar>> {
+        let filters = self.line_filters(direction, index);
+        let mut viable_tiles = vec![HashSet::new(); filters.len()];
+        let all_seen = |viable_tiles: &[HashSet<PairChar>]| viable_tiles.iter().zip(&filters).all(|(x, y)| x.len() == y.len());
+
+        let mut words = BigramIndexTree::get_candidate_words(self.index(direction), &filters);
+        for _ in 0..VIABLE_WORD_LIMIT {
+            let word = match words.next() {
+                Some(v) => v,
+                None => return viable_tiles,
+            };
+            for (i, tile) in word.slice().iter().enumerate() {
+                viable_tiles[i].insert(*tile);
+            }
+            if all_seen(&viable_tiles) {
+                return viable_tiles;
+            }
+        }
+        filters
+    }
+
+    // the cells which no word fits, along both their row and column
+    pub fn dead_cells(&self) -> Vec<(usize, usize)> {
+        let rows: Vec<Vec<HashSet<PairChar>>> =
+            (0..self.depth()).map(|y| self.viable_line_tiles(Direction::Across, y)).collect();
+        let columns: Vec<Vec<HashSet<PairChar>>> =
+            (0..self.width()).map(|x| self.viable_line_tiles(Direction::Down, x)).collect();
+
+        let mut dead_cells: Vec<(usize, usize)> = Vec::new();
+        for y in 0..self.depth() {
+            for x in 0..self.width() {
+                if rows[y][x].is_disjoint(&columns[x][y]) {
+                    dead_cells.push((x, y));
+                }
+            }
+        }
+        dead_cells
+    }
+
+    // fill in the open tiles with the backtracking solver, keeping everything placed so far
+    // false (and the grid left alone) if there's no way to finish it
+    pub fn autocomplete(&mut self) -> bool {
+        self.autocomplete_until(&AtomicBool::new(true))
+    }
+
+    // as autocomplete, but giving up once continue_running is cleared
+    pub fn autocomplete_until(&mut self, continue_running: &AtomicBool) -> bool {
+        let seed_grid = self.grid();
+        let top_start_words =
+            super::generate_seeded_top_words(&seed_grid, self.horizontal_index, self.vertical_index);
+
+        match super::populate_seeded_grid_until(
+            &seed_grid,
+            &top_start_words,
+            self.horizontal_index,
+            self.vertical_index,
+            continue_running,
+            None,
+        ) {
+            Some(v) => {
+                self.save_state();
+                self.rows = grid_tiles(&v);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// the tiles of a grid row by row, blanks included
+fn grid_tiles(grid: &PuzzleGrid) -> Vec<Vec<PairChar>> {
+    (0..grid.depth())
+        .map(|y| (0..grid.width()).map(|x| grid.get_tile(x, y)).collect())
+        .collect()
+}
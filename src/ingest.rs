@@ -7,11 +7,19 @@ pub fn read_even_words(src_file: &str) -> Vec<String> {
     let file = File::open(src_file).unwrap();
     let reader = BufReader::new(file);
 
+    // lines which aren't valid utf-8 are skipped rather than ending the word list, a read
+    // error does end it
     reader
-        .lines()
-        .filter_map(Result::ok)
-        .filter(|x| x.len() % 2 == 0)
-        .filter(|x| x.chars().all(|y| y.is_ascii_lowercase()))
-        .map(|x| x.to_string())
+        .split(b'\n')
+        .map_while(Result::ok)
+        .filter_map(|x| String::from_utf8(x).ok())
+        .map(|x| x.trim_end_matches('\r').to_string())
+        .filter(|x| is_even_word(x))
         .collect()
 }
+
+// check whether a word can be stored as pairchars: all-lowercase-ascii with an even number
+// of characters
+pub fn is_even_word(word: &str) -> bool {
+    !word.is_empty() && word.len() % 2 == 0 && word.chars().all(|x| x.is_ascii_lowercase())
+}
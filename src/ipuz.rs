@@ -0,0 +1,125 @@
+use super::clues;
+use super::puzzlegrid::{Direction, PuzzleGrid};
+use super::types::PairChar;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+const IPUZ_VERSION: &str = "http://ipuz.org/v2";
+const IPUZ_KIND: &str = "http://ipuz.org/crossword#1";
+const BLOCK: &str = "#";
+
+// write a grid as an ipuz crossword, each pair tile becomes a two letter rebus cell, blanks
+// become blocks and cells outside a shape are omitted (null)
+// clues are looked up by answer, entries without a clue just carry their answer
+pub fn write_ipuz(grid: &PuzzleGrid, clues: Option<&HashMap<String, String>>) -> Value {
+    let entries = grid.entries();
+
+    let mut puzzle: Vec<Vec<Value>> = Vec::new();
+    let mut solution: Vec<Vec<Value>> = Vec::new();
+    for y in 0..grid.depth() {
+        let mut puzzle_row: Vec<Value> = Vec::new();
+        let mut solution_row: Vec<Value> = Vec::new();
+        for x in 0..grid.width() {
+            let tile = grid.get_tile(x, y);
+            if tile.is_outside() {
+                puzzle_row.push(Value::Null);
+                solution_row.push(Value::Null);
+            } else if tile.is_blank() {
+                puzzle_row.push(json!(BLOCK));
+                solution_row.push(json!(BLOCK));
+            } else {
+                let number = entries.iter().find(|e| e.x == x && e.y == y).map_or(0, |e| e.number);
+                puzzle_row.push(json!(number));
+                solution_row.push(json!(tile.decode().to_uppercase()));
+            }
+        }
+        puzzle.push(puzzle_row);
+        solution.push(solution_row);
+    }
+
+    let clue_list = |direction: Direction| -> Vec<Value> {
+        entries
+            .iter()
+            .filter(|entry| entry.direction == direction)
+            .map(|entry| {
+                let mut clue = json!({
+                    "number": entry.number,
+                    "answer": entry.word.decode().to_uppercase(),
+                });
+                if let Some(text) = clues::clue_for(entry, clues) {
+                    clue["clue"] = json!(text);
+                }
+                clue
+            })
+            .collect()
+    };
+
+    json!({
+        "version": IPUZ_VERSION,
+        "kind": [IPUZ_KIND],
+        "dimensions": { "width": grid.width(), "height": grid.depth() },
+        "block": BLOCK,
+        "empty": 0,
+        "puzzle": puzzle,
+        "solution": solution,
+        "clues": {
+            "Across": clue_list(Direction::Across),
+            "Down": clue_list(Direction::Down),
+        },
+    })
+}
+
+// read the solution of an ipuz crossword back into a grid, blocks become blanks, omitted cells
+// are outside of the grid and cells with no solution are left as wildcards, so the grid can
+// be validated or used as a seed
+pub fn read_ipuz(ipuz_text: &str) -> Result<PuzzleGrid, String> {
+    let ipuz: Value = serde_json::from_str(ipuz_text).map_err(|e| format!("Invalid ipuz json: {}", e))?;
+
+    let width = ipuz["dimensions"]["width"]
+        .as_u64()
+        .ok_or("Missing ipuz dimensions")? as usize;
+    let depth = ipuz["dimensions"]["height"]
+        .as_u64()
+        .ok_or("Missing ipuz dimensions")? as usize;
+    let block = ipuz["block"].as_str().unwrap_or(BLOCK);
+
+    // without a solution the puzzle still gives the blocks and omitted cells
+    let cells = if ipuz["solution"].is_array() {
+        &ipuz["solution"]
+    } else {
+        &ipuz["puzzle"]
+    };
+
+    // a null cell is outside the grid, but a missing one is a broken file
+    let cell_rows = cells.as_array().ok_or("Missing ipuz puzzle")?;
+    if cell_rows.len() != depth {
+        return Err(format!("Expected {} ipuz rows, found {}", depth, cell_rows.len()));
+    }
+
+    let mut rows: Vec<Vec<PairChar>> = Vec::new();
+    for (y, cell_row) in cell_rows.iter().enumerate() {
+        let cell_row = cell_row.as_array().ok_or(format!("Ipuz row {} isn't a list of cells", y))?;
+        if cell_row.len() != width {
+            return Err(format!("Expected {} ipuz cells in row {}, found {}", width, y, cell_row.len()));
+        }
+
+        let mut row: Vec<PairChar> = Vec::new();
+        for (x, cell) in cell_row.iter().enumerate() {
+            // cells can be plain values, or objects carrying the value alongside styling
+            let value = if cell.is_object() { &cell["value"] } else { cell };
+
+            let tile = match value {
+                Value::Null if cell.is_null() => PairChar::outside(),
+                Value::String(v) if v == block => PairChar::encode(b'_', b'_'),
+                Value::String(v) if !v.is_empty() => {
+                    PairChar::parse(&v.to_lowercase()).map_err(|e| format!("{} at {} {}", e, x, y))?
+                }
+                _ => PairChar::encode(b'?', b'?'),
+            };
+            row.push(tile);
+        }
+        rows.push(row);
+    }
+
+    PuzzleGrid::from_tiles(&rows)
+}
@@ -1,16 +1,32 @@
 pub mod bigramindex;
+pub mod clues;
+pub mod editor;
 pub mod ingest;
+pub mod ipuz;
+pub mod puz;
+pub mod puzzlecube;
 pub mod puzzlegrid;
+pub mod render;
+pub mod report;
+pub mod rpc;
+pub mod server;
+pub mod service;
+pub mod shape;
+pub mod template;
+pub mod tui;
 pub mod types;
+pub mod validate;
 pub mod wordstore;
 
 use bigramindex::BigramIndexTree;
+use puzzlecube::{CubeIndices, PuzzleCube};
 use puzzlegrid::PuzzleGrid;
-use types::WordList;
+use shape::ShapeSolver;
+use types::{PairString, WordList};
 use wordstore::WordStore;
 
 use rayon::prelude::*;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 
 pub fn generate_wordstore(source_file: &str) -> wordstore::WordStore {
@@ -23,6 +39,134 @@ pub fn generate_wordstore(source_file: &str) -> wordstore::WordStore {
     word_store
 }
 
+// add a word to a word store, and to each of the (line size, index) pairs built from it
+// returns false if the word can't be stored
+pub fn insert_word(
+    word: &str,
+    word_store: &mut WordStore,
+    indices: &mut [(usize, &mut BigramIndexTree)],
+    max_blanks: usize,
+) -> bool {
+    if !ingest::is_even_word(word) {
+        return false;
+    }
+
+    let pair_word = PairString::encode(word);
+    if !word_store.contains(&pair_word) {
+        word_store.add(word);
+    }
+    for (size, index) in indices.iter_mut() {
+        index.insert_word(&pair_word, *size, max_blanks);
+    }
+
+    true
+}
+
+// remove a word from a word store, and from each of the (line size, index) pairs built from it
+// returns whether the word was in the word store
+pub fn remove_word(
+    word: &str,
+    word_store: &mut WordStore,
+    indices: &mut [(usize, &mut BigramIndexTree)],
+    max_blanks: usize,
+) -> bool {
+    if !ingest::is_even_word(word) {
+        return false;
+    }
+
+    let pair_word = PairString::encode(word);
+    for (size, index) in indices.iter_mut() {
+        index.remove_word(&pair_word, *size, max_blanks);
+    }
+
+    word_store.remove(word)
+}
+
+// fill out a shaped grid, trying each of the words for the first run of the shape in parallel
+pub fn populate_shape(solver: &ShapeSolver) -> Option<PuzzleGrid> {
+    let continue_running = AtomicBool::new(true);
+    let puzzle_arc = Arc::new(Mutex::<Option<PuzzleGrid>>::new(None));
+
+    let empty_grid = solver.new_grid();
+    let empty_cursors = solver.new_cursors();
+
+    solver.step_candidates(0, &empty_cursors).par_iter().for_each(|x| {
+        let mut puzzle_grid = empty_grid.clone();
+        let mut cursors = empty_cursors.clone();
+
+        if continue_running.load(Ordering::Relaxed)
+            && solver.try_step(&mut puzzle_grid, &mut cursors, 0, x, Some(&continue_running))
+        {
+            continue_running.store(false, Ordering::Relaxed);
+
+            let puzzle_mutex = puzzle_arc.clone();
+            let mut puzzle_guard = puzzle_mutex.lock().unwrap();
+            *puzzle_guard = Some(puzzle_grid);
+        }
+    });
+
+    let puzzle_mutex = puzzle_arc.clone();
+    let mut puzzle_guard = puzzle_mutex.lock().unwrap();
+    (*puzzle_guard).take()
+}
+
+// fill out a word cube slice by slice, trying each of the words for the top row of the
+// bottom slice in parallel
+pub fn populate_cube(width: usize, depth: usize, height: usize, indices: CubeIndices) -> Option<PuzzleCube> {
+    let continue_running = AtomicBool::new(true);
+    let puzzle_arc = Arc::new(Mutex::<Option<PuzzleCube>>::new(None));
+
+    let empty_cube = PuzzleCube::new(width, depth, height);
+    let empty_cursors = empty_cube.new_cursors(indices.pillar);
+    let empty_slice = empty_cube.next_slice(&empty_cursors);
+
+    PuzzleCube::slice_top_words(&empty_slice, indices).par_iter().for_each(|x| {
+        let mut puzzle_cube = empty_cube.clone();
+        let mut cursors = empty_cursors.clone();
+        let mut slice = empty_slice.clone();
+
+        if continue_running.load(Ordering::Relaxed)
+            && puzzle_cube.try_slice(&mut slice, x, indices, &mut cursors, Some(&continue_running))
+        {
+            continue_running.store(false, Ordering::Relaxed);
+
+            let puzzle_mutex = puzzle_arc.clone();
+            let mut puzzle_guard = puzzle_mutex.lock().unwrap();
+            *puzzle_guard = Some(puzzle_cube);
+        }
+    });
+
+    let puzzle_mutex = puzzle_arc.clone();
+    let mut puzzle_guard = puzzle_mutex.lock().unwrap();
+    (*puzzle_guard).take()
+}
+
+// estimated peak memory needed to build the horizontal and (if different) vertical indices
+pub fn estimate_index_bytes(width: usize, depth: usize, word_store: &WordStore, max_blanks: usize) -> usize {
+    let mut estimated_bytes = BigramIndexTree::estimate(width, word_store, max_blanks).bytes();
+    if width != depth {
+        estimated_bytes += BigramIndexTree::estimate(depth, word_store, max_blanks).bytes();
+    }
+
+    estimated_bytes
+}
+
+// find the largest max_blanks (no more than the one requested) whose indices fit in the limit
+pub fn max_blanks_within_limit(
+    width: usize,
+    depth: usize,
+    word_store: &WordStore,
+    max_blanks: usize,
+    limit: usize,
+) -> Option<usize> {
+    // blanks never fill more than half of a line
+    let highest_useful = std::cmp::min(max_blanks, std::cmp::max(width, depth) / 2);
+
+    (0..=highest_useful)
+        .rev()
+        .find(|x| estimate_index_bytes(width, depth, word_store, *x) <= limit)
+}
+
 pub fn generate_top_words(width: usize, word_store: &WordStore, max_blanks: usize, index: &BigramIndexTree) -> WordList {
     // not all words are valid on the top line, only those whose pairchars are all valid
     // starting pairchars of other words,
@@ -33,7 +177,7 @@ pub fn generate_top_words(width: usize, word_store: &WordStore, max_blanks: usiz
 
     // could possibly speed this up by permuting the words afterwards
     'outer: for candidate_word in word_store.permuted_words_by_length(width, max_blanks).clone() {
-        'inner: for pairchar in candidate_word.slice() {
+        for pairchar in candidate_word.slice() {
             if !first_character_set.contains(pairchar) {
                 continue 'outer;
             }
@@ -44,6 +188,19 @@ pub fn generate_top_words(width: usize, word_store: &WordStore, max_blanks: usiz
     result_set
 }
 
+// start words for a grid with constrained tiles (from a seed grid or a template), taken from
+// the horizontal index and keeping only those which fit the top row
+pub fn generate_seeded_top_words(
+    seed_grid: &PuzzleGrid,
+    horizontal_index: &BigramIndexTree,
+    vertical_index: &BigramIndexTree,
+) -> WordList {
+    let mut filters = vec![vertical_index.get_keys_as_hashset(); seed_grid.width()];
+    seed_grid.restrict_to_constraints(0, &mut filters);
+
+    BigramIndexTree::get_candidate_words(horizontal_index, &filters).collect()
+}
+
 // TODO use a result instead of an option for the return wrapper
 pub fn populate_grid(
     width: usize,
@@ -51,21 +208,57 @@ pub fn populate_grid(
     top_start_words: &WordList,
     horizontal_index: &BigramIndexTree,
     vertical_index: &BigramIndexTree,
+) -> Option<PuzzleGrid> {
+    populate_seeded_grid(
+        &PuzzleGrid::new(width, height),
+        top_start_words,
+        horizontal_index,
+        vertical_index,
+    )
+}
+
+// fill out the rest of a grid whose fixed tiles have been read in with PuzzleGrid::parse
+pub fn populate_seeded_grid(
+    seed_grid: &PuzzleGrid,
+    top_start_words: &WordList,
+    horizontal_index: &BigramIndexTree,
+    vertical_index: &BigramIndexTree,
 ) -> Option<PuzzleGrid> {
     let continue_running = AtomicBool::new(true);
+    populate_seeded_grid_until(
+        seed_grid,
+        top_start_words,
+        horizontal_index,
+        vertical_index,
+        &continue_running,
+        None,
+    )
+}
+
+// as populate_seeded_grid, but the search also stops (returning None) once continue_running
+// is cleared from another thread, so long solves can be cancelled
+// top_words_tried (if given) counts the start words whose search has finished, for progress
+pub fn populate_seeded_grid_until(
+    seed_grid: &PuzzleGrid,
+    top_start_words: &WordList,
+    horizontal_index: &BigramIndexTree,
+    vertical_index: &BigramIndexTree,
+    continue_running: &AtomicBool,
+    top_words_tried: Option<&AtomicUsize>,
+) -> Option<PuzzleGrid> {
     let puzzle_arc = Arc::new(Mutex::<Option<PuzzleGrid>>::new(None));
 
     // top_start_words.par_iter().for_each(|x| {
     top_start_words.par_iter().for_each(|x| {
-        let mut puzzle_grid: PuzzleGrid = PuzzleGrid::new(width, height);
+        let mut puzzle_grid: PuzzleGrid = seed_grid.clone();
+        puzzle_grid.reset();
 
         if continue_running.load(Ordering::Relaxed) {
-            let found_result = (&mut puzzle_grid).populate_layer(
-                &x,
-                0,
+            let found_result = puzzle_grid.populate_layer(
+                x,
                 horizontal_index,
                 vertical_index,
-                Some(&continue_running),
+                Some(continue_running),
             );
             if found_result {
                 continue_running.store(false, Ordering::Relaxed);
@@ -76,6 +269,9 @@ pub fn populate_grid(
                 *puzzle_guard = Some(puzzle_grid);
             }
         }
+        if let Some(v) = top_words_tried {
+            v.fetch_add(1, Ordering::Relaxed);
+        }
     });
 
     let puzzle_mutex = puzzle_arc.clone();
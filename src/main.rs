@@ -1,11 +1,32 @@
-use clap::{App, Arg};
-use puzzler;
-use puzzler::bigramindex::BigramIndexTree;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use puzzler::bigramindex::{BigramIndexTree, IndexCache};
+use puzzler::clues;
+use puzzler::editor::GridEditor;
+use puzzler::ipuz;
+use puzzler::puz;
+use puzzler::puzzlecube::CubeIndices;
+use puzzler::puzzlegrid::{BlankRules, BlankSymmetry, PuzzleGrid, Repeats};
+use puzzler::render;
+use puzzler::report::RunReport;
+use puzzler::rpc;
+use puzzler::server;
+use puzzler::service::Service;
+use puzzler::shape::{Shape, ShapeSolver};
+use puzzler::template::Template;
+use puzzler::tui;
+use puzzler::validate;
+use puzzler::wordstore::{LineMode, WordStore, Wrap};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::net::TcpListener;
+use std::sync::Arc;
 
 fn main() {
     // parse commandline
     let matches = App::new("Puzzler")
         .version("0.1")
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(
             Arg::with_name("debug")
                 .help("turn on debugging")
@@ -29,71 +50,786 @@ fn main() {
                 .default_value("512")
                 .long("maxblanks"),
         )
+        .arg(
+            Arg::with_name("memorylimit")
+                .help(
+                    "Refuse to build indices estimated to need more than this many megabytes \
+                     (only estimated for joined lines, so not checked with --line-mode separated)",
+                )
+                .short("m")
+                .takes_value(true)
+                .conflicts_with_all(&["template", "toroidal", "height"])
+                .long("memory-limit"),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .help("Partially filled grid to complete, with ?? marking the tiles to fill in (or an .ipuz file)")
+                .short("s")
+                .takes_value(true)
+                .long("seed"),
+        )
+        .arg(
+            Arg::with_name("template")
+                .help("Template marking the cells which must be blank (#) and those which must hold letters (.)")
+                .short("t")
+                .takes_value(true)
+                .long("template"),
+        )
+        .arg(
+            Arg::with_name("blanksymmetry")
+                .help("Symmetry required of the blank tiles")
+                .takes_value(true)
+                .possible_values(&["rotational", "horizontal", "vertical", "none"])
+                .default_value("none")
+                .long("blank-symmetry"),
+        )
+        .arg(
+            Arg::with_name("maxtotalblanks")
+                .help("Have at most this number of blanks in the whole grid")
+                .takes_value(true)
+                .validator(whole_number)
+                .long("max-total-blanks"),
+        )
+        .arg(
+            Arg::with_name("minfill")
+                .help("Fill at least this percentage of the grid with letters")
+                .takes_value(true)
+                .validator(percentage)
+                .long("min-fill"),
+        )
+        .arg(
+            Arg::with_name("noblanklines")
+                .help("Don't allow rows or columns made entirely of blanks")
+                .long("no-blank-lines"),
+        )
+        .arg(
+            Arg::with_name("noadjacentblanks")
+                .help("Don't allow blanks next to each other in a row or column")
+                .long("no-adjacent-blanks"),
+        )
+        .arg(
+            Arg::with_name("linemode")
+                .help("Read each line as one word with the blanks skipped, or as words separated by blanks")
+                .takes_value(true)
+                .possible_values(&["joined", "separated"])
+                .default_value("joined")
+                .long("line-mode"),
+        )
+        .arg(
+            Arg::with_name("singletiles")
+                .help("What a single tile between blanks may hold in separated lines")
+                .takes_value(true)
+                .possible_values(&["forbid", "dictionary", "unchecked"])
+                .default_value("dictionary")
+                .long("single-tiles"),
+        )
+        .arg(
+            Arg::with_name("toroidal")
+                .help("Wrap lines around the grid, accepting any rotation of a word (any) or only words starting at this tile")
+                .takes_value(true)
+                .conflicts_with("template")
+                .long("toroidal"),
+        )
+        .arg(
+            Arg::with_name("diagonals")
+                .help("Make the two main diagonals of a square grid words too (magic square)")
+                .conflicts_with("template")
+                .long("diagonals"),
+        )
+        .arg(
+            Arg::with_name("symmetric")
+                .help("Make a symmetric square, where each row is the same word as the matching column")
+                .long("symmetric"),
+        )
+        .arg(
+            Arg::with_name("allowrepeats")
+                .help("Allow the same word to appear more than once in the grid")
+                .conflicts_with("symmetric")
+                .long("allow-repeats"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .help("Write the solution as space separated tiles (text), with the run details as json, or as an ipuz or Across Lite (puz) crossword")
+                .takes_value(true)
+                .possible_values(&["text", "json", "ipuz", "puz"])
+                .long("format"),
+        )
+        .arg(
+            Arg::with_name("answers")
+                .help("List the numbered across and down entries after the solution")
+                .conflicts_with("format")
+                .long("answers"),
+        )
+        .arg(
+            Arg::with_name("clues")
+                .help("Clue database of word<TAB>clue lines, to attach clues to the entries of the solution")
+                .takes_value(true)
+                .long("clues"),
+        )
+        .arg(
+            Arg::with_name("render")
+                .help("Also draw the solution as an svg, or as an html page with solving and answer views")
+                .takes_value(true)
+                .possible_values(&["svg", "html"])
+                .requires("out")
+                .long("render"),
+        )
+        .arg(
+            Arg::with_name("out")
+                .help("File to write the rendered solution to")
+                .takes_value(true)
+                .requires("render")
+                .long("out"),
+        )
+        .arg(
+            Arg::with_name("height")
+                .help(
+                    "Build a word cube this many slices high, where every line along all three axes is a word \
+                     (written as text or json, the crossword formats, rendering and clues only cover flat grids)",
+                )
+                .takes_value(true)
+                .conflicts_with_all(&[
+                    "seed",
+                    "template",
+                    "blanksymmetry",
+                    "toroidal",
+                    "diagonals",
+                    "symmetric",
+                    "allowrepeats",
+                    "render",
+                    "answers",
+                    "clues",
+                    "maxtotalblanks",
+                    "minfill",
+                    "noblanklines",
+                    "noadjacentblanks",
+                    "linemode",
+                    "memorylimit",
+                ])
+                .long("height"),
+        )
+        .arg(
+            Arg::with_name("shape")
+                .help("Mask marking the cells inside (.) and outside (x) of a non-rectangular grid")
+                .takes_value(true)
+                .conflicts_with_all(&[
+                    "seed",
+                    "template",
+                    "blanksymmetry",
+                    "toroidal",
+                    "diagonals",
+                    "symmetric",
+                    "allowrepeats",
+                    "height",
+                    "maxtotalblanks",
+                    "minfill",
+                    "noblanklines",
+                    "noadjacentblanks",
+                    "memorylimit",
+                ])
+                .long("shape"),
+        )
         .arg(
             Arg::with_name("width")
                 .help("grid width")
-                .required(true),
+                .required_unless_one(&["seed", "template", "shape"]),
         )
         .arg(
             Arg::with_name("depth")
                 .help("grid depth")
-                .required(true),
+                .required_unless_one(&["seed", "template", "shape"]),
+        )
+        .subcommand(
+            SubCommand::with_name("validate")
+                .about("Check that every line of a grid is a word, and that none of them repeat")
+                .arg(
+                    Arg::with_name("grid")
+                        .help("Grid to check, in the format the solver prints (or an .ipuz file)")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("dictionary")
+                        .help("Dictionary file to read from")
+                        .short("D")
+                        .takes_value(true)
+                        .default_value("/usr/share/dict/words")
+                        .long("dictionary"),
+                )
+                .arg(
+                    Arg::with_name("maxblanks")
+                        .help("Allow at most this number of blanks per line")
+                        .short("b")
+                        .takes_value(true)
+                        .default_value("512")
+                        .long("maxblanks"),
+                )
+                .arg(
+                    Arg::with_name("linemode")
+                        .help("Read each line as one word with the blanks skipped, or as words separated by blanks")
+                        .takes_value(true)
+                        .possible_values(&["joined", "separated"])
+                        .default_value("joined")
+                        .long("line-mode"),
+                )
+                .arg(
+                    Arg::with_name("singletiles")
+                        .help("What a single tile between blanks may hold in separated lines")
+                        .takes_value(true)
+                        .possible_values(&["forbid", "dictionary", "unchecked"])
+                        .default_value("dictionary")
+                        .long("single-tiles"),
+                )
+                .arg(
+                    Arg::with_name("toroidal")
+                        .help("Lines wrap around the grid, accepting any rotation of a word (any) or only words starting at this tile")
+                        .takes_value(true)
+                        .long("toroidal"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("edit")
+                .about("Build a grid by hand, with suggestions for each open line")
+                .arg(
+                    Arg::with_name("width")
+                        .help("grid width")
+                        .required_unless("grid"),
+                )
+                .arg(
+                    Arg::with_name("depth")
+                        .help("grid depth")
+                        .required_unless("grid"),
+                )
+                .arg(
+                    Arg::with_name("grid")
+                        .help("Carry on editing a grid, in the format the solver prints (or an .ipuz file)")
+                        .short("g")
+                        .takes_value(true)
+                        .conflicts_with_all(&["width", "depth"])
+                        .long("grid"),
+                )
+                .arg(
+                    Arg::with_name("dictionary")
+                        .help("Dictionary file to read from")
+                        .short("D")
+                        .takes_value(true)
+                        .default_value("/usr/share/dict/words")
+                        .long("dictionary"),
+                )
+                .arg(
+                    Arg::with_name("maxblanks")
+                        .help(
+                            "Allow at most this number of blanks per line (none by default, unlike solving, so the \
+                             index is quick to build and the suggestions are whole words)",
+                        )
+                        .short("b")
+                        .takes_value(true)
+                        .default_value("0")
+                        .long("maxblanks"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("serve")
+                .about("Answer solve, validate and candidate requests over http on localhost")
+                .arg(
+                    Arg::with_name("port")
+                        .help("Port to listen on")
+                        .short("p")
+                        .takes_value(true)
+                        .default_value("8000")
+                        .long("port"),
+                )
+                .arg(
+                    Arg::with_name("dictionary")
+                        .help("Dictionary for requests which don't name one")
+                        .short("D")
+                        .takes_value(true)
+                        .default_value("/usr/share/dict/words")
+                        .long("dictionary"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("rpc")
+                .about("Answer newline delimited json requests on stdin, keeping indices loaded between them")
+                .arg(
+                    Arg::with_name("dictionary")
+                        .help("Dictionary for requests which don't name one")
+                        .short("D")
+                        .takes_value(true)
+                        .default_value("/usr/share/dict/words")
+                        .long("dictionary"),
+                ),
         )
         .get_matches();
 
+    if let Some(m) = matches.subcommand_matches("rpc") {
+        let service = Arc::new(Service::new(m.value_of("dictionary").unwrap()));
+        rpc::run_rpc(service, std::io::stdin().lock(), std::io::stdout());
+        return;
+    }
+    if let Some(m) = matches.subcommand_matches("serve") {
+        serve(m);
+        return;
+    }
+    if let Some(m) = matches.subcommand_matches("validate") {
+        validate(m);
+        return;
+    }
+    if let Some(m) = matches.subcommand_matches("edit") {
+        edit(m);
+        return;
+    }
+
     let dictionary_file = matches.value_of("dictionary").unwrap();
-    let puzzle_width: usize = matches.value_of("width").unwrap().parse::<usize>().unwrap();
-    let puzzle_depth: usize = matches.value_of("depth").unwrap().parse::<usize>().unwrap();
+    let seed_grid: Option<PuzzleGrid> = matches.value_of("seed").map(|x| {
+        read_grid(x).unwrap_or_else(|e| {
+            eprintln!("Failed to read seed grid {}: {}", x, e);
+            std::process::exit(1);
+        })
+    });
+    let template: Option<Template> = matches.value_of("template").map(|x| {
+        let template_text = fs::read_to_string(x).unwrap();
+        Template::parse(&template_text).unwrap_or_else(|e| {
+            eprintln!("Failed to read template {}: {}", x, e);
+            std::process::exit(1);
+        })
+    });
+    let shape: Option<Shape> = matches.value_of("shape").map(|x| {
+        let shape_text = fs::read_to_string(x).unwrap();
+        Shape::parse(&shape_text).unwrap_or_else(|e| {
+            eprintln!("Failed to read shape {}: {}", x, e);
+            std::process::exit(1);
+        })
+    });
+    let puzzle_width: usize = match matches.value_of("width") {
+        Some(v) => v.parse::<usize>().unwrap(),
+        None => seed_grid
+            .as_ref()
+            .map(PuzzleGrid::width)
+            .or_else(|| template.as_ref().map(Template::width))
+            .unwrap_or_else(|| shape.as_ref().unwrap().width()),
+    };
+    let puzzle_depth: usize = match matches.value_of("depth") {
+        Some(v) => v.parse::<usize>().unwrap(),
+        None => seed_grid
+            .as_ref()
+            .map(PuzzleGrid::depth)
+            .or_else(|| template.as_ref().map(Template::depth))
+            .unwrap_or_else(|| shape.as_ref().unwrap().depth()),
+    };
+    let blank_symmetry: BlankSymmetry = matches.value_of("blanksymmetry").unwrap().parse().unwrap();
+    let blank_rules = BlankRules {
+        max_total_blanks: matches.value_of("maxtotalblanks").map(|x| x.parse::<usize>().unwrap()),
+        min_fill_percent: matches.value_of("minfill").map(|x| x.parse::<usize>().unwrap()),
+        no_blank_lines: matches.is_present("noblanklines"),
+        no_adjacent_blanks: matches.is_present("noadjacentblanks"),
+    };
+    let line_mode = match matches.value_of("linemode").unwrap() {
+        "separated" => LineMode::Separated(matches.value_of("singletiles").unwrap().parse().unwrap()),
+        _ => LineMode::Joined,
+    };
+    let wrap: Wrap = matches.value_of("toroidal").unwrap_or("none").parse().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let repeats = if matches.is_present("symmetric") {
+        Repeats::Symmetric
+    } else if matches.is_present("allowrepeats") {
+        Repeats::Allowed
+    } else {
+        Repeats::Distinct
+    };
     let max_blanks: usize = matches.value_of("maxblanks").unwrap().parse::<usize>().unwrap();
     let debug: bool = matches.is_present("debug");
+    let output = OutputOptions {
+        format: matches.value_of("format").unwrap_or("text"),
+        render: matches.value_of("render"),
+        out: matches.value_of("out"),
+        answers: matches.is_present("answers"),
+        clues: matches.value_of("clues").map(|x| {
+            let clue_text = fs::read_to_string(x).unwrap();
+            clues::read_clues(&clue_text).unwrap_or_else(|e| {
+                eprintln!("Failed to read clues {}: {}", x, e);
+                std::process::exit(1);
+            })
+        }),
+    };
+    let memory_limit: Option<usize> = matches.value_of("memorylimit").map(|x| {
+        x.parse::<usize>()
+            .ok()
+            .and_then(|v| v.checked_mul(1024 * 1024))
+            .unwrap_or_else(|| {
+                eprintln!("Invalid memory limit: {} (expected a number of megabytes)", x);
+                std::process::exit(1);
+            })
+    });
+
+    let mut seed_grid = match seed_grid {
+        Some(v) => {
+            if v.width() != puzzle_width || v.depth() != puzzle_depth {
+                eprintln!(
+                    "Seed grid is {}x{}, but a {}x{} grid was requested",
+                    v.width(),
+                    v.depth(),
+                    puzzle_width,
+                    puzzle_depth
+                );
+                std::process::exit(1);
+            }
+            v
+        }
+        None => PuzzleGrid::new(puzzle_width, puzzle_depth),
+    };
+
+    seed_grid.set_blank_symmetry(blank_symmetry);
+    seed_grid.set_blank_rules(blank_rules);
+    seed_grid.set_line_mode(line_mode);
+    seed_grid.set_wrap(wrap);
+    if let Err(e) = seed_grid.set_diagonals(matches.is_present("diagonals")) {
+        eprintln!("Failed to set diagonals: {}", e);
+        std::process::exit(1);
+    }
+    if let Err(e) = seed_grid.set_repeats(repeats) {
+        eprintln!("Failed to set repeats: {}", e);
+        std::process::exit(1);
+    }
+    if let Some(t) = &template {
+        if let Err(e) = seed_grid.apply_template(t) {
+            eprintln!("Failed to apply template: {}", e);
+            std::process::exit(1);
+        }
+    }
 
     // collect all of the source words, and store by length
-    println!("Extracting Word List from {}", dictionary_file);
+    let mut report = RunReport::new(puzzle_width, puzzle_depth, max_blanks);
+    eprintln!("Extracting Word List from {}", dictionary_file);
     let word_store = puzzler::generate_wordstore(dictionary_file);
+    report.set_dictionary_fingerprint(word_store.fingerprint());
+    if let Some(c) = &output.clues {
+        report.set_clues(c.clone());
+    }
+    report.finish_stage("dictionary");
+
+    // cubes are filled a slice at a time by the grid solver, with an index for each axis
+    if let Some(h) = matches.value_of("height") {
+        let puzzle_height = h.parse::<usize>().unwrap_or_else(|e| {
+            eprintln!("Invalid height: {}", e);
+            std::process::exit(1);
+        });
+        if output.format != "text" && output.format != "json" {
+            eprintln!("Cubes can only be written as text or json");
+            std::process::exit(1);
+        }
+        solve_cube(puzzle_width, puzzle_depth, puzzle_height, &word_store, max_blanks, &output, report);
+        return;
+    }
+
+    // shaped grids have their own solver, with an index for each length of line in the shape
+    if let Some(s) = &shape {
+        solve_shape(s, &word_store, max_blanks, line_mode, &output, report);
+        return;
+    }
+
+    // check that the indices will fit in memory before we start building them
+    // (the estimate only covers joined lines, clap turns the limit down for templates, toroidal
+    // grids and cubes)
+    if let Some(limit) = memory_limit.filter(|_| line_mode == LineMode::Joined) {
+        let estimated_bytes = puzzler::estimate_index_bytes(puzzle_width, puzzle_depth, &word_store, max_blanks);
+        if estimated_bytes > limit {
+            eprintln!(
+                "Estimated index size of {}MB exceeds the memory limit of {}MB",
+                estimated_bytes / (1024 * 1024),
+                limit / (1024 * 1024)
+            );
+            match puzzler::max_blanks_within_limit(puzzle_width, puzzle_depth, &word_store, max_blanks, limit) {
+                Some(v) => eprintln!("Try running with --maxblanks {}", v),
+                None => eprintln!("No value of --maxblanks fits within the limit"),
+            }
+            std::process::exit(1);
+        }
+    }
 
     // generate two indices
-    println!("Building Indices");
-    let horizontal_index: BigramIndexTree = BigramIndexTree::build(puzzle_width, &word_store, max_blanks);
+    eprintln!("Building Indices");
+    let build_wrapped = |size: usize| {
+        let lines = word_store.lines_by_length(size, max_blanks, line_mode);
+        BigramIndexTree::build_from_words(size, WordStore::wrap_lines(lines, wrap))
+    };
+    let horizontal_index: BigramIndexTree = match &template {
+        Some(t) => BigramIndexTree::build_for_patterns(puzzle_width, &word_store, &t.row_patterns(), line_mode),
+        None => build_wrapped(puzzle_width),
+    };
     if debug {
         horizontal_index.print("");
     }
 
-    // only generate a real vertical index if the grid is not square
-    let vertical_index = if puzzle_width == puzzle_depth {
+    // only generate a real vertical index if the rows and columns need different indices
+    let share_index = match &template {
+        Some(t) => t.row_patterns() == t.column_patterns(),
+        None => puzzle_width == puzzle_depth,
+    };
+    let vertical_index = if share_index {
         // allocate an empty instance just to simplify code flow
         BigramIndexTree::new(0)
     } else {
-        BigramIndexTree::build(puzzle_depth, &word_store, max_blanks)
+        match &template {
+            Some(t) => BigramIndexTree::build_for_patterns(puzzle_depth, &word_store, &t.column_patterns(), line_mode),
+            None => build_wrapped(puzzle_depth),
+        }
     };
-    let vertical_index_ref = if puzzle_width == puzzle_depth {
+    let vertical_index_ref = if share_index {
         &horizontal_index
     } else {
         &vertical_index
     };
 
+    report.finish_stage("indices");
+
     // build the start words
-    println!("Building Top Layer Candidates");
-    let top_start_words = puzzler::generate_top_words(puzzle_width, &word_store, max_blanks, vertical_index_ref);
+    eprintln!("Building Top Layer Candidates");
+    let top_start_words = puzzler::generate_seeded_top_words(&seed_grid, &horizontal_index, vertical_index_ref);
+    report.finish_stage("top_words");
 
     // build out the puzzle_grid, building a second index if necessary
-    println!("Populating the puzzle grid");
-    let puzzle_grid = puzzler::populate_grid(
-        puzzle_width,
-        puzzle_depth,
+    eprintln!("Populating the puzzle grid");
+    let puzzle_grid = puzzler::populate_seeded_grid(
+        &seed_grid,
         &top_start_words,
         &horizontal_index,
         vertical_index_ref,
     );
+    report.finish_stage("populate");
 
     // print out the grid if successful
-    match puzzle_grid {
-        // Some(p) => {p.print(); println!("{:?}",p.get_rows());},
-        Some(p) => p.print(),
-        None => {
-            println!(
-                "No matches found for size {}x{}",
-                puzzle_width, puzzle_depth
-            );
+    let failure = format!("No matches found for size {}x{}", puzzle_width, puzzle_depth);
+    print_solution(&output, &report, puzzle_grid.as_ref(), &failure);
+}
+
+// clap validators, so that bad numbers get a usage error rather than a panic
+fn whole_number(value: String) -> Result<(), String> {
+    value.parse::<usize>().map(|_| ()).map_err(|_| "expected a whole number".to_string())
+}
+
+fn percentage(value: String) -> Result<(), String> {
+    match value.parse::<usize>() {
+        Ok(v) if v <= 100 => Ok(()),
+        _ => Err("expected a whole number from 0 to 100".to_string()),
+    }
+}
+
+// read a grid in the text format written by PuzzleGrid::print, or from an ipuz file
+fn read_grid(grid_file: &str) -> Result<PuzzleGrid, String> {
+    let grid_text = fs::read_to_string(grid_file).map_err(|e| e.to_string())?;
+    if grid_file.ends_with(".ipuz") {
+        ipuz::read_ipuz(&grid_text)
+    } else {
+        PuzzleGrid::parse(&grid_text)
+    }
+}
+
+// the validate subcommand, report each line of the grid which breaks the rules and exit with
+// an error if there are any
+fn validate(matches: &ArgMatches) {
+    let grid_file = matches.value_of("grid").unwrap();
+    let grid = read_grid(grid_file).unwrap_or_else(|e| {
+        eprintln!("Failed to read grid {}: {}", grid_file, e);
+        std::process::exit(1);
+    });
+    let max_blanks: usize = matches.value_of("maxblanks").unwrap().parse::<usize>().unwrap();
+    let line_mode = match matches.value_of("linemode").unwrap() {
+        "separated" => LineMode::Separated(matches.value_of("singletiles").unwrap().parse().unwrap()),
+        _ => LineMode::Joined,
+    };
+    let wrap: Wrap = matches.value_of("toroidal").unwrap_or("none").parse().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+
+    let dictionary_file = matches.value_of("dictionary").unwrap();
+    eprintln!("Extracting Word List from {}", dictionary_file);
+    let word_store = puzzler::generate_wordstore(dictionary_file);
+
+    let invalid_lines = validate::validate_grid(&grid, &word_store, max_blanks, line_mode, wrap);
+    if invalid_lines.is_empty() {
+        println!("{} is valid", grid_file);
+        return;
+    }
+
+    for invalid_line in &invalid_lines {
+        eprintln!("{}", invalid_line);
+    }
+    std::process::exit(1);
+}
+
+// the serve subcommand, only listening on localhost
+fn serve(matches: &ArgMatches) {
+    let port: u16 = matches.value_of("port").unwrap().parse::<u16>().unwrap_or_else(|e| {
+        eprintln!("Invalid port: {}", e);
+        std::process::exit(1);
+    });
+    let listener = TcpListener::bind(("127.0.0.1", port)).unwrap_or_else(|e| {
+        eprintln!("Failed to listen on port {}: {}", port, e);
+        std::process::exit(1);
+    });
+
+    eprintln!("Listening on http://127.0.0.1:{}", port);
+    server::serve(listener, Arc::new(Service::new(matches.value_of("dictionary").unwrap())));
+}
+
+// the edit subcommand, build the indices for the grid's lines and hand over to the terminal editor
+fn edit(matches: &ArgMatches) {
+    let grid: Option<PuzzleGrid> = matches.value_of("grid").map(|x| {
+        read_grid(x).unwrap_or_else(|e| {
+            eprintln!("Failed to read grid {}: {}", x, e);
+            std::process::exit(1);
+        })
+    });
+    let size = |name: &str| -> usize {
+        let value = matches.value_of(name).unwrap();
+        value.parse::<usize>().ok().filter(|x| *x > 0).unwrap_or_else(|| {
+            eprintln!("Invalid {}: {} (expected a number of tiles, at least 1)", name, value);
+            std::process::exit(1);
+        })
+    };
+    let (width, depth) = match &grid {
+        Some(g) => (g.width(), g.depth()),
+        None => (size("width"), size("depth")),
+    };
+    let max_blanks: usize = matches.value_of("maxblanks").unwrap().parse::<usize>().unwrap();
+
+    let dictionary_file = matches.value_of("dictionary").unwrap();
+    eprintln!("Extracting Word List from {}", dictionary_file);
+    let word_store = puzzler::generate_wordstore(dictionary_file);
+
+    eprintln!("Building Indices");
+    let mut index_cache = IndexCache::new(max_blanks, LineMode::Joined);
+    index_cache.get_or_build(width, &word_store);
+    index_cache.get_or_build(depth, &word_store);
+    let horizontal_index = index_cache.get(width).unwrap();
+    let vertical_index = index_cache.get(depth).unwrap();
+
+    let editor = match &grid {
+        Some(g) => GridEditor::from_grid(g, horizontal_index, vertical_index),
+        None => GridEditor::new(width, depth, horizontal_index, vertical_index),
+    };
+    let mut editor = editor.unwrap_or_else(|e| {
+        eprintln!("Failed to start the editor: {}", e);
+        std::process::exit(1);
+    });
+
+    if let Err(e) = tui::run_editor(&mut editor) {
+        eprintln!("Failed to run the editor: {}", e);
+        std::process::exit(1);
+    }
+}
+
+// how to write out a solution, from the command line
+struct OutputOptions<'a> {
+    format: &'a str,
+    render: Option<&'a str>,
+    out: Option<&'a str>,
+    answers: bool,
+    clues: Option<HashMap<String, String>>,
+}
+
+// write out the solution as plain tiles, as a json document with the details of the run, or
+// as an ipuz or .puz crossword, drawing it to a file as well if asked
+fn print_solution(output: &OutputOptions, report: &RunReport, solution: Option<&PuzzleGrid>, failure: &str) {
+    let clues = output.clues.as_ref();
+    if let (Some(c), Some(p)) = (clues, solution) {
+        for entry in clues::missing_clues(p, c) {
+            eprintln!("Missing clue for {}", entry);
+        }
+    }
+
+    if let (Some(render), Some(out), Some(p)) = (output.render, output.out, solution) {
+        let rendered = match render {
+            "html" => render::render_html(p, "Puzzler", clues),
+            _ => render::render_svg(p, true, true),
+        };
+        if let Err(e) = fs::write(out, rendered) {
+            eprintln!("Failed to write {}: {}", out, e);
+            std::process::exit(1);
+        }
+    }
+
+    match (output.format, solution) {
+        ("json", _) => println!("{}", serde_json::to_string_pretty(&report.to_json(solution)).unwrap()),
+        ("ipuz", Some(p)) => println!("{}", serde_json::to_string_pretty(&ipuz::write_ipuz(p, clues)).unwrap()),
+        ("puz", Some(p)) => match puz::write_puz(p, "", clues) {
+            Ok(v) => std::io::stdout().write_all(&v).unwrap(),
+            Err(e) => {
+                eprintln!("Failed to write .puz file: {}", e);
+                std::process::exit(1);
+            }
+        },
+        (_, Some(p)) => {
+            p.print();
+            if output.answers {
+                println!();
+                for entry in p.entries() {
+                    match clues::clue_for(&entry, clues) {
+                        Some(clue) => println!("{} - {}", entry, clue),
+                        None => println!("{}", entry),
+                    }
+                }
+            }
         }
+        (_, None) => println!("{}", failure),
+    }
+}
+
+fn solve_cube(
+    width: usize,
+    depth: usize,
+    height: usize,
+    word_store: &WordStore,
+    max_blanks: usize,
+    output: &OutputOptions,
+    mut report: RunReport,
+) {
+    eprintln!("Building Indices");
+    // cube lines are always read joined, which is why --height conflicts with --line-mode
+    let mut index_cache = IndexCache::new(max_blanks, LineMode::Joined);
+    for length in [width, depth, height] {
+        index_cache.get_or_build(length, word_store);
     }
+    report.finish_stage("indices");
+
+    eprintln!("Populating the puzzle cube");
+    let indices = CubeIndices {
+        horizontal: index_cache.get(width).unwrap(),
+        vertical: index_cache.get(depth).unwrap(),
+        pillar: index_cache.get(height).unwrap(),
+    };
+    let puzzle_cube = puzzler::populate_cube(width, depth, height, indices);
+    report.finish_stage("populate");
+
+    match (output.format, puzzle_cube) {
+        ("json", c) => println!("{}", serde_json::to_string_pretty(&report.cube_to_json(height, c.as_ref())).unwrap()),
+        (_, Some(c)) => c.print(),
+        (_, None) => println!("No matches found for cube"),
+    }
+}
+
+fn solve_shape(
+    shape: &Shape,
+    word_store: &WordStore,
+    max_blanks: usize,
+    line_mode: LineMode,
+    output: &OutputOptions,
+    mut report: RunReport,
+) {
+    eprintln!("Building Indices");
+    let mut index_cache = IndexCache::new(max_blanks, line_mode);
+    for length in shape.line_lengths() {
+        index_cache.get_or_build(length, word_store);
+    }
+    report.finish_stage("indices");
+
+    eprintln!("Populating the puzzle grid");
+    let solver = ShapeSolver::new(shape, &index_cache).unwrap();
+    let puzzle_grid = puzzler::populate_shape(&solver);
+    report.finish_stage("populate");
+
+    print_solution(output, &report, puzzle_grid.as_ref(), "No matches found for shape");
 }
@@ -0,0 +1,255 @@
+use super::clues;
+use super::puzzlegrid::PuzzleGrid;
+use super::types::PairChar;
+use std::collections::HashMap;
+
+const MAGIC: &[u8] = b"ACROSS&DOWN\0";
+const VERSION: &[u8] = b"1.3\0";
+const HEADER_SIZE: usize = 0x34;
+const BLACK: u8 = b'.';
+const EMPTY: u8 = b'-';
+const MASK: &[u8] = b"ICHEATED";
+
+// A .puz file read back in, with the clues in file order (by number, across before down)
+pub struct PuzFile {
+    pub grid: PuzzleGrid,
+    pub title: String,
+    pub clues: Vec<String>,
+}
+
+// the running checksum used throughout the format
+fn checksum_region(data: &[u8], checksum: u16) -> u16 {
+    data.iter().fold(checksum, |checksum, byte| {
+        checksum.rotate_right(1).wrapping_add(u16::from(*byte))
+    })
+}
+
+// checksum over the title, author, copyright, clues and notes, the strings are only counted
+// (with their terminators) when they're not empty
+fn text_checksum(strings: &[String; 3], clues: &[String], notes: &str, mut checksum: u16) -> u16 {
+    for text in strings.iter().filter(|x| !x.is_empty()) {
+        checksum = checksum_region(text.as_bytes(), checksum);
+        checksum = checksum_region(b"\0", checksum);
+    }
+    for clue in clues {
+        checksum = checksum_region(clue.as_bytes(), checksum);
+    }
+    if !notes.is_empty() {
+        checksum = checksum_region(notes.as_bytes(), checksum);
+        checksum = checksum_region(b"\0", checksum);
+    }
+    checksum
+}
+
+// the header checksums: the file checksum, the checksum of the dimensions and clue count
+// (the cib) and the masked checksums of each part, all of which are checked by solving apps
+fn header_checksums(
+    header: &[u8],
+    solution: &[u8],
+    fill: &[u8],
+    strings: &[String; 3],
+    clues: &[String],
+    notes: &str,
+) -> (u16, u16, [u8; 8]) {
+    let cib_checksum = checksum_region(&header[0x2C..0x34], 0);
+    let solution_checksum = checksum_region(solution, 0);
+    let fill_checksum = checksum_region(fill, 0);
+    let text_part = text_checksum(strings, clues, notes, 0);
+
+    let mut file_checksum = checksum_region(solution, cib_checksum);
+    file_checksum = checksum_region(fill, file_checksum);
+    file_checksum = text_checksum(strings, clues, notes, file_checksum);
+
+    let parts = [cib_checksum, solution_checksum, fill_checksum, text_part];
+    let mut masked = [0u8; 8];
+    for (i, part) in parts.iter().enumerate() {
+        masked[i] = MASK[i] ^ (part & 0xFF) as u8;
+        masked[i + 4] = MASK[i + 4] ^ (part >> 8) as u8;
+    }
+
+    (file_checksum, cib_checksum, masked)
+}
+
+// an extra section of the file, with its own length and checksum
+fn write_section(bytes: &mut Vec<u8>, title: &[u8], data: &[u8]) {
+    bytes.extend_from_slice(title);
+    bytes.extend_from_slice(&(data.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(&checksum_region(data, 0).to_le_bytes());
+    bytes.extend_from_slice(data);
+    bytes.push(0);
+}
+
+// write a grid as an Across Lite .puz file, blanks and cells outside a shape become black
+// squares and every pair tile goes in the rebus section, with its first letter in the
+// solution grid for apps which don't read rebuses
+// clues are looked up by answer and left empty if missing
+pub fn write_puz(grid: &PuzzleGrid, title: &str, clues: Option<&HashMap<String, String>>) -> Result<Vec<u8>, String> {
+    if grid.width() > 255 || grid.depth() > 255 {
+        return Err(format!("A {}x{} grid is too large for a .puz file", grid.width(), grid.depth()));
+    }
+
+    let mut solution: Vec<u8> = Vec::new();
+    let mut rebus_grid: Vec<u8> = Vec::new();
+    let mut rebus_keys: Vec<PairChar> = Vec::new();
+    for y in 0..grid.depth() {
+        for x in 0..grid.width() {
+            let tile = grid.get_tile(x, y);
+            if tile.is_wildcard() {
+                return Err(format!("Tile {} {} hasn't been filled in", x, y));
+            }
+            if tile.is_blank() || tile.is_outside() {
+                solution.push(BLACK);
+                rebus_grid.push(0);
+                continue;
+            }
+
+            let key = match rebus_keys.iter().position(|x| *x == tile) {
+                Some(v) => v,
+                None => {
+                    rebus_keys.push(tile);
+                    rebus_keys.len() - 1
+                }
+            };
+            if key >= 255 {
+                return Err("Too many different tiles for the rebus table".to_string());
+            }
+            solution.push(tile.decode().to_uppercase().as_bytes()[0]);
+            rebus_grid.push(key as u8 + 1);
+        }
+    }
+    let fill: Vec<u8> = solution.iter().map(|x| if *x == BLACK { BLACK } else { EMPTY }).collect();
+
+    let clue_list: Vec<String> = grid
+        .entries()
+        .iter()
+        .map(|entry| clues::clue_for(entry, clues).unwrap_or("").to_string())
+        .collect();
+    let strings = [title.to_string(), String::new(), String::new()];
+
+    let mut header = vec![0u8; HEADER_SIZE];
+    header[0x02..0x0E].copy_from_slice(MAGIC);
+    header[0x18..0x1C].copy_from_slice(VERSION);
+    header[0x2C] = grid.width() as u8;
+    header[0x2D] = grid.depth() as u8;
+    header[0x2E..0x30].copy_from_slice(&(clue_list.len() as u16).to_le_bytes());
+    header[0x30..0x32].copy_from_slice(&1u16.to_le_bytes());
+
+    let (file_checksum, cib_checksum, masked) = header_checksums(&header, &solution, &fill, &strings, &clue_list, "");
+    header[0x00..0x02].copy_from_slice(&file_checksum.to_le_bytes());
+    header[0x0E..0x10].copy_from_slice(&cib_checksum.to_le_bytes());
+    header[0x10..0x18].copy_from_slice(&masked);
+
+    let mut bytes = header;
+    bytes.extend_from_slice(&solution);
+    bytes.extend_from_slice(&fill);
+    for text in strings.iter().chain(clue_list.iter()) {
+        bytes.extend_from_slice(text.as_bytes());
+        bytes.push(0);
+    }
+    // empty notes
+    bytes.push(0);
+
+    if !rebus_keys.is_empty() {
+        let table: String = rebus_keys
+            .iter()
+            .enumerate()
+            .map(|(key, tile)| format!("{:2}:{};", key, tile.decode().to_uppercase()))
+            .collect();
+        write_section(&mut bytes, b"GRBS", &rebus_grid);
+        write_section(&mut bytes, b"RTBL", table.as_bytes());
+    }
+
+    Ok(bytes)
+}
+
+// read a .puz file written by write_puz (or any app) back in, checking its checksums
+// black squares become blanks, and every letter square must be a two letter rebus
+pub fn read_puz(bytes: &[u8]) -> Result<PuzFile, String> {
+    if bytes.len() < HEADER_SIZE || &bytes[0x02..0x0E] != MAGIC {
+        return Err("Not an Across Lite .puz file".to_string());
+    }
+
+    let width = bytes[0x2C] as usize;
+    let depth = bytes[0x2D] as usize;
+    let clue_count = u16::from_le_bytes([bytes[0x2E], bytes[0x2F]]) as usize;
+    let cell_count = width * depth;
+    if bytes.len() < HEADER_SIZE + 2 * cell_count {
+        return Err("The .puz file is too short for its grid".to_string());
+    }
+
+    let solution = &bytes[HEADER_SIZE..(HEADER_SIZE + cell_count)];
+    let fill = &bytes[(HEADER_SIZE + cell_count)..(HEADER_SIZE + 2 * cell_count)];
+
+    // the strings are null terminated: title, author, copyright, the clues, then the notes
+    let mut position = HEADER_SIZE + 2 * cell_count;
+    let mut next_string = || -> Result<String, String> {
+        let length = bytes[position..]
+            .iter()
+            .position(|x| *x == 0)
+            .ok_or("The .puz file ends part way through its text")?;
+        let text = bytes[position..(position + length)].iter().map(|x| *x as char).collect();
+        position += length + 1;
+        Ok(text)
+    };
+    let strings = [next_string()?, next_string()?, next_string()?];
+    let clues = (0..clue_count).map(|_| next_string()).collect::<Result<Vec<String>, String>>()?;
+    let notes = next_string()?;
+
+    let (file_checksum, cib_checksum, masked) = header_checksums(bytes, solution, fill, &strings, &clues, &notes);
+    if bytes[0x00..0x02] != file_checksum.to_le_bytes()
+        || bytes[0x0E..0x10] != cib_checksum.to_le_bytes()
+        || bytes[0x10..0x18] != masked
+    {
+        return Err("The .puz file checksums don't match".to_string());
+    }
+
+    // the extra sections follow the strings, each with a title, length and checksum
+    let mut sections: HashMap<String, &[u8]> = HashMap::new();
+    while position + 8 <= bytes.len() {
+        let title = String::from_utf8_lossy(&bytes[position..(position + 4)]).to_string();
+        let length = u16::from_le_bytes([bytes[position + 4], bytes[position + 5]]) as usize;
+        let checksum = u16::from_le_bytes([bytes[position + 6], bytes[position + 7]]);
+        let data = bytes
+            .get((position + 8)..(position + 8 + length))
+            .ok_or(format!("The .puz section {} is cut short", title))?;
+        if checksum_region(data, 0) != checksum {
+            return Err(format!("The .puz section {} checksum doesn't match", title));
+        }
+        sections.insert(title, data);
+        position += 8 + length + 1;
+    }
+
+    let rebus_grid = sections.get("GRBS").copied().unwrap_or(&[]);
+    let mut rebus_table: HashMap<u8, String> = HashMap::new();
+    for entry in String::from_utf8_lossy(sections.get("RTBL").copied().unwrap_or(&[])).split(';') {
+        if let Some((key, answer)) = entry.split_once(':') {
+            let key = key.trim().parse::<u8>().map_err(|e| format!("Invalid rebus key: {}", e))?;
+            rebus_table.insert(key + 1, answer.to_lowercase());
+        }
+    }
+
+    let mut rows: Vec<Vec<PairChar>> = Vec::new();
+    for y in 0..depth {
+        let mut row: Vec<PairChar> = Vec::new();
+        for x in 0..width {
+            let cell = y * width + x;
+            let tile = if solution[cell] == BLACK {
+                PairChar::encode(b'_', b'_')
+            } else {
+                let answer = rebus_grid
+                    .get(cell)
+                    .and_then(|x| rebus_table.get(x))
+                    .ok_or(format!("Square {} {} isn't a two letter rebus", x, y))?;
+                PairChar::parse(answer).map_err(|e| format!("{} at {} {}", e, x, y))?
+            };
+            row.push(tile);
+        }
+        rows.push(row);
+    }
+
+    Ok(PuzFile {
+        grid: PuzzleGrid::from_tiles(&rows)?,
+        title: strings[0].clone(),
+        clues,
+    })
+}
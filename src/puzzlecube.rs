@@ -0,0 +1,187 @@
+use super::bigramindex::{BigramIndexTree, IndexCursor};
+use super::puzzlegrid::PuzzleGrid;
+use super::types::{PairChar, PairString, WordList};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// The indices for the three axes of a cube: across (width), down (depth) and through the
+// slices (height), any of which may be the same index
+#[derive(Clone, Copy)]
+pub struct CubeIndices<'a> {
+    pub horizontal: &'a BigramIndexTree,
+    pub vertical: &'a BigramIndexTree,
+    pub pillar: &'a BigramIndexTree,
+}
+
+// A word cube of width x depth x height tiles, held as a stack of 2D slices, where each row
+// and column of a slice and each pillar running up through the slices is a word
+#[derive(Clone, Debug)]
+pub struct PuzzleCube {
+    slices: Vec<PuzzleGrid>,
+    width: usize,
+    depth: usize,
+    height: usize,
+}
+
+impl PuzzleCube {
+    pub fn new(width: usize, depth: usize, height: usize) -> PuzzleCube {
+        PuzzleCube {
+            slices: Vec::new(),
+            width,
+            depth,
+            height,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get_tile(&self, x: usize, y: usize, z: usize) -> PairChar {
+        self.slices[z].get_tile(x, y)
+    }
+
+    pub fn get_slices(&self) -> &[PuzzleGrid] {
+        &self.slices
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.slices.len() >= self.height
+    }
+
+    // the tiles placed so far in the line running up through the slices at (x, y)
+    pub fn get_pillar(&self, x: usize, y: usize) -> Vec<PairChar> {
+        self.slices.iter().map(|slice| slice.get_tile(x, y)).collect()
+    }
+
+    // every line in the cube must be a different word, along all three axes
+    pub fn has_duplicates(&self) -> bool {
+        let mut match_set: HashSet<Vec<PairChar>> = HashSet::new();
+
+        let mut lines: Vec<Vec<PairChar>> = Vec::new();
+        for slice in &self.slices {
+            lines.extend(slice.get_rows());
+            lines.extend(slice.get_columns().iter().map(|x| x.to_vec()));
+        }
+        for x in 0..self.width {
+            for y in 0..self.depth {
+                lines.push(self.get_pillar(x, y));
+            }
+        }
+
+        for line in lines {
+            let compact_word: Vec<PairChar> = line.into_iter().filter(|x| !x.is_blank()).collect();
+            if !match_set.insert(compact_word) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    pub fn print(&self) {
+        for (z, slice) in self.slices.iter().enumerate() {
+            if z > 0 {
+                println!();
+            }
+            slice.print();
+        }
+    }
+
+    // a cursor at the bottom of each pillar, indexed by column then row
+    pub fn new_cursors<'a>(&self, pillar_index: &'a BigramIndexTree) -> Vec<Vec<IndexCursor<'a>>> {
+        vec![vec![pillar_index.cursor(); self.depth]; self.width]
+    }
+
+    // an empty grid for the next slice, with each tile limited to the pairchars which can
+    // extend the pillar running through it
+    pub fn next_slice(&self, cursors: &[Vec<IndexCursor>]) -> PuzzleGrid {
+        let mut slice = PuzzleGrid::new(self.width, self.depth);
+        slice.set_tile_filters(
+            cursors
+                .iter()
+                .map(|column| column.iter().map(IndexCursor::possible_pairchars).collect())
+                .collect(),
+        );
+        slice
+    }
+
+    // the words which could go in the top row of a slice
+    pub fn slice_top_words(slice: &PuzzleGrid, indices: CubeIndices) -> WordList {
+        let mut filters = vec![indices.vertical.get_keys_as_hashset(); slice.width()];
+        slice.restrict_to_constraints(0, &mut filters);
+
+        BigramIndexTree::get_candidate_words(indices.horizontal, &filters).collect()
+    }
+
+    // fill out a slice starting from word, using the grid solver, and for each way of
+    // completing it try to fill out the slices above, undoing the slice if that fails
+    pub fn try_slice<'a>(
+        &mut self,
+        slice: &mut PuzzleGrid,
+        word: &PairString,
+        indices: CubeIndices<'a>,
+        cursors: &mut [Vec<IndexCursor<'a>>],
+        continue_running: Option<&AtomicBool>,
+    ) -> bool {
+        slice.populate_layer_with(
+            word,
+            indices.horizontal,
+            indices.vertical,
+            continue_running,
+            &mut |solved_slice| {
+                for (x, column) in cursors.iter_mut().enumerate() {
+                    for (y, cursor) in column.iter_mut().enumerate() {
+                        cursor.descend(solved_slice.get_tile(x, y));
+                    }
+                }
+                self.slices.push(solved_slice.clone());
+
+                if self.populate_slice(indices, cursors, continue_running) {
+                    return true;
+                }
+
+                self.slices.pop();
+                for cursor in cursors.iter_mut().flat_map(|x| x.iter_mut()) {
+                    cursor.ascend();
+                }
+                false
+            },
+        )
+    }
+
+    // recursion function for populate_cube
+    fn populate_slice<'a>(
+        &mut self,
+        indices: CubeIndices<'a>,
+        cursors: &mut [Vec<IndexCursor<'a>>],
+        continue_running: Option<&AtomicBool>,
+    ) -> bool {
+        if continue_running.is_some_and(|x| !x.load(Ordering::Relaxed)) {
+            return false;
+        }
+
+        if self.is_complete() {
+            // the slices and pillars are all words, but may repeat each other
+            return !self.has_duplicates();
+        }
+
+        let empty_slice = self.next_slice(cursors);
+        for word in PuzzleCube::slice_top_words(&empty_slice, indices) {
+            let mut slice = empty_slice.clone();
+            if self.try_slice(&mut slice, &word, indices, cursors, continue_running) {
+                return true;
+            }
+        }
+
+        false
+    }
+}
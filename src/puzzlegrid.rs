@@ -1,11 +1,134 @@
-use super::types::{PairChar, PairString, WordIterator};
+use super::types::{PairChar, PairString};
 use std::collections::HashSet;
-use super::bigramindex::{BigramIndexTree};
+use std::fmt;
+use std::str::FromStr;
+use super::bigramindex::{BigramIndexTree, IndexCursor};
+use super::shape::Shape;
+use super::template::Template;
+use super::wordstore::{LineMode, Wrap};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+// Restrictions on what the solver may place in a single tile of the grid
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TileConstraint {
+    Any,
+    Letters,
+    Fixed(PairChar),
+}
+
+impl TileConstraint {
+    pub fn allows(&self, pairchar: PairChar) -> bool {
+        match self {
+            TileConstraint::Any => true,
+            TileConstraint::Letters => !pairchar.is_blank(),
+            TileConstraint::Fixed(v) => *v == pairchar,
+        }
+    }
+}
+
+// Symmetry required of the blank tiles in a grid, as in published crosswords
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum BlankSymmetry {
+    #[default]
+    None,
+    Rotational,
+    Horizontal,
+    Vertical,
+}
+
+impl FromStr for BlankSymmetry {
+    type Err = String;
+
+    fn from_str(symmetry: &str) -> Result<BlankSymmetry, String> {
+        match symmetry {
+            "none" => Ok(BlankSymmetry::None),
+            "rotational" => Ok(BlankSymmetry::Rotational),
+            "horizontal" => Ok(BlankSymmetry::Horizontal),
+            "vertical" => Ok(BlankSymmetry::Vertical),
+            _ => Err(format!("Unknown blank symmetry: {}", symmetry)),
+        }
+    }
+}
+
+// How the words of a finished grid may repeat each other: all different, any repeats at
+// all, or a symmetric square where each row is the same word as the matching column
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Repeats {
+    #[default]
+    Distinct,
+    Allowed,
+    Symmetric,
+}
+
+// Which way a line of the grid runs
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Direction {
+    Across,
+    Down,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Direction::Across => write!(f, "across"),
+            Direction::Down => write!(f, "down"),
+        }
+    }
+}
+
+// A numbered word of the grid, running across or down from its start cell for length tiles:
+// blanks end an entry, as do the edges of the grid or shape, as in a printed crossword
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Entry {
+    pub number: usize,
+    pub x: usize,
+    pub y: usize,
+    pub direction: Direction,
+    pub length: usize,
+    pub word: PairString,
+}
+
+impl Entry {
+    // the cells covered by the entry, from its start
+    pub fn cells(&self) -> Vec<(usize, usize)> {
+        (0..self.length)
+            .map(|i| match self.direction {
+                Direction::Across => (self.x + i, self.y),
+                Direction::Down => (self.x, self.y + i),
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} ({}, {}) {}", self.number, self.direction, self.x, self.y, self.word)
+    }
+}
+
+// Grid-wide limits on the blanks, so that solutions look like real puzzles
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct BlankRules {
+    pub max_total_blanks: Option<usize>,
+    pub min_fill_percent: Option<usize>,
+    pub no_blank_lines: bool,
+    pub no_adjacent_blanks: bool,
+}
 
 #[derive(Clone, Default, Debug)]
 pub struct PuzzleGrid {
     columns: Vec<Vec<PairChar>>,
+    constraints: Vec<Vec<TileConstraint>>,
+    tile_filters: Option<Vec<Vec<HashSet<PairChar>>>>,
+    blank_symmetry: BlankSymmetry,
+    blank_rules: BlankRules,
+    line_mode: LineMode,
+    wrap: Wrap,
+    diagonals: bool,
+    repeats: Repeats,
+    deadline: Option<Instant>,
+    placed_blanks: usize,
     next_layer: usize,
     width: usize,
     depth: usize,
@@ -15,12 +138,393 @@ impl PuzzleGrid {
     pub fn new(width: usize, depth: usize) -> PuzzleGrid {
         PuzzleGrid {
             columns: vec![vec![PairChar::encode(b'u', b'u'); depth]; width],
+            constraints: vec![vec![TileConstraint::Any; depth]; width],
+            tile_filters: None,
+            blank_symmetry: BlankSymmetry::None,
+            blank_rules: BlankRules::default(),
+            line_mode: LineMode::Joined,
+            wrap: Wrap::Flat,
+            diagonals: false,
+            repeats: Repeats::Distinct,
+            deadline: None,
+            placed_blanks: 0,
             next_layer: 0,
             width,
             depth,
         }
     }
 
+    // read a grid in the format written by print, with "??" marking a wildcard tile
+    // every other tile is fixed, so the solver will only fill in the wildcards
+    pub fn parse(grid_text: &str) -> Result<PuzzleGrid, String> {
+        let mut rows: Vec<Vec<PairChar>> = Vec::new();
+
+        for (line_number, line) in grid_text.lines().enumerate() {
+            let row = line
+                .split_whitespace()
+                .map(PairChar::parse)
+                .collect::<Result<Vec<PairChar>, String>>()
+                .map_err(|e| format!("{} on line {}", e, line_number + 1))?;
+
+            if row.is_empty() {
+                continue;
+            }
+
+            if !rows.is_empty() && row.len() != rows[0].len() {
+                return Err(format!(
+                    "Expected {} tiles on line {}, found {}",
+                    rows[0].len(),
+                    line_number + 1,
+                    row.len()
+                ));
+            }
+            rows.push(row);
+        }
+
+        PuzzleGrid::from_tiles(&rows)
+    }
+
+    // build a grid from rows of tiles, fixing every tile which isn't a wildcard
+    pub fn from_tiles(rows: &[Vec<PairChar>]) -> Result<PuzzleGrid, String> {
+        if rows.is_empty() || rows[0].is_empty() {
+            return Err("No tiles found in grid".to_string());
+        }
+        if let Some(y) = rows.iter().position(|x| x.len() != rows[0].len()) {
+            return Err(format!("Expected {} tiles in row {}, found {}", rows[0].len(), y + 1, rows[y].len()));
+        }
+
+        let mut grid = PuzzleGrid::new(rows[0].len(), rows.len());
+        for (y, row) in rows.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                grid.columns[x][y] = *tile;
+                if !tile.is_wildcard() {
+                    grid.constraints[x][y] = TileConstraint::Fixed(*tile);
+                }
+            }
+        }
+        grid.next_layer = grid.depth;
+        grid.placed_blanks = rows.iter().flatten().filter(|x| x.is_blank()).count();
+
+        Ok(grid)
+    }
+
+    // an empty grid covering a shape's bounding box, the cells outside of the shape are fixed
+    pub fn from_shape(shape: &Shape) -> PuzzleGrid {
+        let mut grid = PuzzleGrid::new(shape.width(), shape.depth());
+        for x in 0..grid.width {
+            for y in 0..grid.depth {
+                if !shape.is_inside(x, y) {
+                    grid.columns[x][y] = PairChar::outside();
+                    grid.constraints[x][y] = TileConstraint::Fixed(PairChar::outside());
+                }
+            }
+        }
+
+        // shaped grids are filled run by run rather than layer by layer
+        grid.next_layer = grid.depth;
+
+        grid
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn get_tile(&self, x: usize, y: usize) -> PairChar {
+        self.columns[x][y]
+    }
+
+    pub fn set_tile(&mut self, x: usize, y: usize, pairchar: PairChar) {
+        self.columns[x][y] = pairchar;
+    }
+
+    // mark the template's blank cells as fixed blanks, and every other cell as needing a letter
+    pub fn apply_template(&mut self, template: &Template) -> Result<(), String> {
+        if template.width() != self.width || template.depth() != self.depth {
+            return Err(format!(
+                "Template is {}x{}, but the grid is {}x{}",
+                template.width(),
+                template.depth(),
+                self.width,
+                self.depth
+            ));
+        }
+
+        let blank = PairChar::encode(b'_', b'_');
+        for x in 0..self.width {
+            for y in 0..self.depth {
+                let template_constraint = if template.is_blank(x, y) {
+                    TileConstraint::Fixed(blank)
+                } else {
+                    TileConstraint::Letters
+                };
+
+                self.constraints[x][y] = match self.constraints[x][y] {
+                    TileConstraint::Any => template_constraint,
+                    TileConstraint::Fixed(v) if template_constraint.allows(v) => TileConstraint::Fixed(v),
+                    _ => return Err(format!("Tile {} {} conflicts with the template", x, y)),
+                };
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn set_blank_symmetry(&mut self, blank_symmetry: BlankSymmetry) {
+        self.blank_symmetry = blank_symmetry;
+    }
+
+    // the tile which must mirror (x, y) under the blank symmetry
+    fn mirror_tile(&self, x: usize, y: usize) -> (usize, usize) {
+        match self.blank_symmetry {
+            BlankSymmetry::None => (x, y),
+            BlankSymmetry::Rotational => (self.width - 1 - x, self.depth - 1 - y),
+            BlankSymmetry::Horizontal => (x, self.depth - 1 - y),
+            BlankSymmetry::Vertical => (self.width - 1 - x, y),
+        }
+    }
+
+    // check that a word placed at the next layer keeps the blanks symmetric, both with itself
+    // and with the layers above it
+    fn fits_symmetry(&self, word: &PairString) -> bool {
+        (0..self.width).all(|x| {
+            let (mirror_x, mirror_y) = self.mirror_tile(x, self.next_layer);
+            if mirror_y == self.next_layer {
+                word[mirror_x].is_blank() == word[x].is_blank()
+            } else if mirror_y < self.next_layer {
+                self.columns[mirror_x][mirror_y].is_blank() == word[x].is_blank()
+            } else {
+                true
+            }
+        })
+    }
+
+    // narrow the filters for the next layer to match the blanks mirrored from the layers above
+    fn restrict_to_symmetry(&self, filters: &mut [HashSet<PairChar>]) {
+        for (x, filter) in filters.iter_mut().enumerate() {
+            let (mirror_x, mirror_y) = self.mirror_tile(x, self.next_layer);
+            if mirror_y < self.next_layer {
+                let mirror_is_blank = self.columns[mirror_x][mirror_y].is_blank();
+                filter.retain(|pairchar| pairchar.is_blank() == mirror_is_blank);
+            }
+        }
+    }
+
+    pub fn line_mode(&self) -> LineMode {
+        self.line_mode
+    }
+
+    pub fn set_line_mode(&mut self, line_mode: LineMode) {
+        self.line_mode = line_mode;
+    }
+
+    // give up the search once the deadline has passed, as though it had been stopped
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    pub fn is_past_deadline(&self) -> bool {
+        self.deadline.is_some_and(|x| Instant::now() >= x)
+    }
+
+    pub fn wrap(&self) -> Wrap {
+        self.wrap
+    }
+
+    pub fn set_wrap(&mut self, wrap: Wrap) {
+        self.wrap = wrap;
+    }
+
+    pub fn diagonals(&self) -> bool {
+        self.diagonals
+    }
+
+    // require the two main diagonals of a square grid to be words as well, for magic squares
+    pub fn set_diagonals(&mut self, diagonals: bool) -> Result<(), String> {
+        if diagonals && self.width != self.depth {
+            return Err(format!(
+                "Diagonals need a square grid, not {}x{}",
+                self.width, self.depth
+            ));
+        }
+        self.diagonals = diagonals;
+        Ok(())
+    }
+
+    // the tiles placed so far along the leading and trailing diagonals
+    pub fn get_diagonal_stems(&self) -> (Vec<PairChar>, Vec<PairChar>) {
+        let leading = (0..self.next_layer).map(|y| self.columns[y][y]).collect();
+        let trailing = (0..self.next_layer)
+            .map(|y| self.columns[self.width - 1 - y][y])
+            .collect();
+        (leading, trailing)
+    }
+
+    // narrow the filters for the diagonal cells of the next layer to the tiles which can
+    // extend the diagonal stems, the diagonals are as long as the rows so share their index
+    fn restrict_to_diagonals(&self, diagonal_index: &BigramIndexTree, filters: &mut [HashSet<PairChar>]) {
+        if !self.diagonals || self.next_layer == 0 || self.next_layer >= self.depth {
+            return;
+        }
+
+        let (leading, trailing) = self.get_diagonal_stems();
+        let possibles = diagonal_index.get_possible_pairchars(vec![&leading, &trailing]);
+        filters[self.next_layer].retain(|x| possibles[0].contains(x));
+        filters[self.width - 1 - self.next_layer].retain(|x| possibles[1].contains(x));
+    }
+
+    pub fn repeats(&self) -> Repeats {
+        self.repeats
+    }
+
+    pub fn set_repeats(&mut self, repeats: Repeats) -> Result<(), String> {
+        if repeats == Repeats::Symmetric && self.width != self.depth {
+            return Err(format!(
+                "A symmetric grid needs to be square, not {}x{}",
+                self.width, self.depth
+            ));
+        }
+        self.repeats = repeats;
+        Ok(())
+    }
+
+    // in a symmetric square the tiles left of the diagonal are copies of the column above,
+    // so check a word placed at the next layer matches them
+    fn fits_repeats(&self, word: &PairString) -> bool {
+        self.repeats != Repeats::Symmetric
+            || (0..self.next_layer).all(|x| word[x] == self.columns[self.next_layer][x])
+    }
+
+    // fix the tiles left of the diagonal to those mirrored from the rows above, so only the
+    // upper triangle of a symmetric square is free to fill
+    fn restrict_to_repeats(&self, filters: &mut [HashSet<PairChar>]) {
+        if self.repeats != Repeats::Symmetric {
+            return;
+        }
+
+        for (x, filter) in filters.iter_mut().enumerate().take(self.next_layer) {
+            let mirror_pairchar = self.columns[self.next_layer][x];
+            filter.retain(|pairchar| *pairchar == mirror_pairchar);
+        }
+    }
+
+    // check whether every row of the grid is the same as the matching column
+    pub fn is_symmetric(&self) -> bool {
+        self.width == self.depth
+            && (0..self.depth).all(|y| (0..self.width).all(|x| self.columns[x][y] == self.columns[y][x]))
+    }
+
+    // whether a completed grid breaks the rules on repeated words, symmetric squares only
+    // need their rows and diagonals to differ as the columns are the rows again
+    pub fn breaks_repeats(&self) -> bool {
+        match self.repeats {
+            Repeats::Distinct => self.has_duplicates(),
+            Repeats::Allowed => false,
+            Repeats::Symmetric => !self.is_symmetric() || self.has_duplicates(),
+        }
+    }
+
+    pub fn set_blank_rules(&mut self, blank_rules: BlankRules) {
+        self.blank_rules = blank_rules;
+    }
+
+    // the most blanks the whole grid may hold under the blank rules
+    fn blank_budget(&self) -> usize {
+        let tile_count = self.width * self.depth;
+        let fill_budget = self
+            .blank_rules
+            .min_fill_percent
+            .map_or(tile_count, |x| tile_count * (100 - x.min(100)) / 100);
+
+        self.blank_rules.max_total_blanks.map_or(fill_budget, |x| x.min(fill_budget))
+    }
+
+    // check that a word placed at the next layer keeps the grid within the blank rules
+    fn fits_blank_rules(&self, word: &PairString) -> bool {
+        let word_blanks = word.slice().iter().filter(|x| x.is_blank()).count();
+        if self.placed_blanks + word_blanks > self.blank_budget() {
+            return false;
+        }
+
+        if self.blank_rules.no_blank_lines {
+            if word_blanks == self.width {
+                return false;
+            }
+
+            // columns can only be checked once the last layer is placed
+            let is_last_layer = self.next_layer + 1 == self.depth;
+            let is_blank_column =
+                |x: usize| word[x].is_blank() && self.columns[x][..self.next_layer].iter().all(|y| y.is_blank());
+            if is_last_layer && (0..self.width).any(is_blank_column) {
+                return false;
+            }
+        }
+
+        if self.blank_rules.no_adjacent_blanks {
+            let blank_beside = word.slice().windows(2).any(|x| x[0].is_blank() && x[1].is_blank());
+            let blank_above = self.next_layer > 0
+                && (0..self.width).any(|x| word[x].is_blank() && self.columns[x][self.next_layer - 1].is_blank());
+            if blank_beside || blank_above {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    // narrow the filters for the next layer to rule out blanks the blank rules won't allow
+    fn restrict_to_blank_rules(&self, filters: &mut [HashSet<PairChar>]) {
+        let budget_spent = self.placed_blanks >= self.blank_budget();
+
+        for (x, filter) in filters.iter_mut().enumerate() {
+            let blank_above = self.next_layer > 0 && self.columns[x][self.next_layer - 1].is_blank();
+            if budget_spent || (self.blank_rules.no_adjacent_blanks && blank_above) {
+                filter.retain(|pairchar| !pairchar.is_blank());
+            }
+        }
+    }
+
+    // limit each tile to a set of pairchars, indexed by column then row, for constraints
+    // coming from outside the grid such as the third axis of a word cube
+    pub fn set_tile_filters(&mut self, tile_filters: Vec<Vec<HashSet<PairChar>>>) {
+        self.tile_filters = Some(tile_filters);
+    }
+
+    // check that a word agrees with all of the tile constraints in a layer of the grid
+    pub fn fits_layer(&self, word: &PairString, layer: usize) -> bool {
+        word.len() == self.width
+            && self
+                .constraints
+                .iter()
+                .zip(word.slice())
+                .all(|(column, pairchar)| column[layer].allows(*pairchar))
+            && self.tile_filters.as_ref().map_or(true, |tile_filters| {
+                tile_filters
+                    .iter()
+                    .zip(word.slice())
+                    .all(|(column, pairchar)| column[layer].contains(pairchar))
+            })
+    }
+
+    // narrow the filters for a layer down to the pairchars allowed by its tile constraints
+    pub fn restrict_to_constraints(&self, layer: usize, filters: &mut [HashSet<PairChar>]) {
+        for (column, filter) in self.constraints.iter().zip(filters.iter_mut()) {
+            if column[layer] != TileConstraint::Any {
+                filter.retain(|x| column[layer].allows(*x));
+            }
+        }
+
+        if let Some(tile_filters) = &self.tile_filters {
+            for (column, filter) in tile_filters.iter().zip(filters.iter_mut()) {
+                filter.retain(|x| column[layer].contains(x));
+            }
+        }
+    }
+
     pub fn is_complete(&self) -> bool {
         self.next_layer >= self.depth
     }
@@ -29,12 +533,12 @@ impl PuzzleGrid {
     // TODO: replace the Option with a Result
     pub fn add_layer(&mut self, word: &PairString) -> Option<()> {
         if self.next_layer >= self.depth {
-            println!("Depth too large");
+            eprintln!("Depth too large");
             return None;
         }
 
         if word.len() != self.width {
-            println!("Word too long: columns={} word={}", self.width, word.len());
+            eprintln!("Word too long: columns={} word={}", self.width, word.len());
             return None;
         }
 
@@ -42,6 +546,7 @@ impl PuzzleGrid {
             self.columns[i][self.next_layer] = word.pair_string[i];
         }
 
+        self.placed_blanks += word.slice().iter().filter(|x| x.is_blank()).count();
         self.next_layer += 1;
 
         Some(())
@@ -50,11 +555,13 @@ impl PuzzleGrid {
     pub fn remove_layer(&mut self) {
         if self.next_layer > 0 {
             self.next_layer -= 1;
+            self.placed_blanks -= self.columns.iter().filter(|x| x[self.next_layer].is_blank()).count();
         }
     }
 
     pub fn reset(&mut self) {
         self.next_layer = 0;
+        self.placed_blanks = 0;
     }
 
     pub fn get_rows(&self) -> Vec<Vec<PairChar>> {
@@ -82,71 +589,282 @@ impl PuzzleGrid {
         return_val
     }
 
+    // the columns of a symmetric square are its rows again, so only the rows are compared
     pub fn has_duplicates(&self) -> bool {
         let mut match_set: HashSet<Vec<PairChar>> = HashSet::new();
 
-        for column in self.get_columns() {
-            let compact_word = PuzzleGrid::clone_without_blanks(column);
-            if match_set.contains(&compact_word) {
-                return true;
-            } else {
-                match_set.insert(compact_word);
+        if let LineMode::Separated(_) = self.line_mode {
+            // every word of more than one tile in the grid must be different, single tiles are
+            // too short to count as repeats, and on a toroidal grid rotations of a word repeat it
+            let mut lines: Vec<Vec<PairChar>> = match self.repeats {
+                Repeats::Symmetric => Vec::new(),
+                _ => self.get_columns().iter().map(|x| x.to_vec()).collect(),
+            };
+            for y in 0..self.next_layer {
+                lines.push(self.columns.iter().map(|x| x[y]).collect());
             }
+            if self.diagonals {
+                let (leading, trailing) = self.get_diagonal_stems();
+                lines.push(leading);
+                lines.push(trailing);
+            }
+
+            for line in lines {
+                for word in line.split(|x| x.is_blank()).filter(|x| x.len() > 1) {
+                    if !match_set.insert(self.wrap_word(word.to_vec())) {
+                        return true;
+                    }
+                }
+            }
+
+            return false;
         }
 
-        for row in self.get_rows() {
-            let compact_word = PuzzleGrid::clone_without_blanks(&row);
-            if match_set.contains(&compact_word) {
-                return true;
-            } else {
-                match_set.insert(compact_word);
+        let columns = match self.repeats {
+            Repeats::Symmetric => Vec::new(),
+            _ => self.get_columns(),
+        };
+
+        for column in columns {
+            for segment in PuzzleGrid::shape_segments(column) {
+                let compact_word = self.wrap_word(PuzzleGrid::clone_without_blanks(segment));
+                if match_set.contains(&compact_word) {
+                    return true;
+                } else {
+                    match_set.insert(compact_word);
+                }
+            }
+        }
+
+        let mut rows = self.get_rows();
+        if self.diagonals {
+            let (leading, trailing) = self.get_diagonal_stems();
+            rows.push(leading);
+            rows.push(trailing);
+        }
+
+        for row in rows {
+            for segment in PuzzleGrid::shape_segments(&row) {
+                let compact_word = self.wrap_word(PuzzleGrid::clone_without_blanks(segment));
+                if match_set.contains(&compact_word) {
+                    return true;
+                } else {
+                    match_set.insert(compact_word);
+                }
             }
         }
 
         false
     }
 
+    // on a toroidal grid all the rotations of a word count as the same word, so pick the
+    // lowest rotation to compare them by
+    pub(crate) fn wrap_word(&self, word: Vec<PairChar>) -> Vec<PairChar> {
+        if self.wrap == Wrap::Flat {
+            return word;
+        }
+
+        (0..word.len())
+            .map(|x| {
+                let mut rotated_word = word.clone();
+                rotated_word.rotate_left(x);
+                rotated_word
+            })
+            .min()
+            .unwrap_or(word)
+    }
+
+    // split a line of a shaped grid into the lines within the shape, ignoring single cells
+    fn shape_segments(line: &[PairChar]) -> Vec<&[PairChar]> {
+        if !line.iter().any(PairChar::is_outside) {
+            return vec![line];
+        }
+
+        line.split(PairChar::is_outside).filter(|x| x.len() > 1).collect()
+    }
+
+    // number the cells which start an entry in reading order, as in a printed crossword, and
+    // list the entries by number with across before down
+    pub fn entries(&self) -> Vec<Entry> {
+        let is_letter = |x: usize, y: usize| {
+            let tile = self.columns[x][y];
+            !tile.is_blank() && !tile.is_outside()
+        };
+
+        let mut entries: Vec<Entry> = Vec::new();
+        let mut next_number = 1;
+        for y in 0..self.depth {
+            for x in 0..self.width {
+                if !is_letter(x, y) {
+                    continue;
+                }
+
+                let across_length = (x..self.width).take_while(|i| is_letter(*i, y)).count();
+                let down_length = (y..self.depth).take_while(|i| is_letter(x, *i)).count();
+                let starts_across = (x == 0 || !is_letter(x - 1, y)) && across_length > 1;
+                let starts_down = (y == 0 || !is_letter(x, y - 1)) && down_length > 1;
+
+                if starts_across {
+                    let row: Vec<PairChar> = (x..(x + across_length)).map(|i| self.columns[i][y]).collect();
+                    entries.push(Entry {
+                        number: next_number,
+                        x,
+                        y,
+                        direction: Direction::Across,
+                        length: across_length,
+                        word: PairString::assemble(&row),
+                    });
+                }
+                if starts_down {
+                    entries.push(Entry {
+                        number: next_number,
+                        x,
+                        y,
+                        direction: Direction::Down,
+                        length: down_length,
+                        word: PairString::assemble(&self.columns[x][y..(y + down_length)]),
+                    });
+                }
+                if starts_across || starts_down {
+                    next_number += 1;
+                }
+            }
+        }
+
+        entries
+    }
+
+    // the words reading across each row, in reading order, with the blanks taken out (or
+    // split on, for separated lines)
+    pub fn across_words(&self) -> Vec<PairString> {
+        (0..self.depth)
+            .flat_map(|y| {
+                let row: Vec<PairChar> = self.columns.iter().map(|x| x[y]).collect();
+                self.line_words(&row)
+            })
+            .collect()
+    }
+
+    // the words reading down each column, from left to right
+    pub fn down_words(&self) -> Vec<PairString> {
+        self.columns.iter().flat_map(|x| self.line_words(x)).collect()
+    }
+
+    fn line_words(&self, line: &[PairChar]) -> Vec<PairString> {
+        let mut words: Vec<PairString> = Vec::new();
+        for segment in PuzzleGrid::shape_segments(line) {
+            match self.line_mode {
+                LineMode::Joined => words.push(PairString::assemble(&PuzzleGrid::clone_without_blanks(segment))),
+                LineMode::Separated(_) => words.extend(
+                    segment
+                        .split(|x| x.is_blank())
+                        .filter(|x| x.len() > 1)
+                        .map(PairString::assemble),
+                ),
+            }
+        }
+        words
+    }
+
     fn clone_without_blanks(pairchar_slice: &[PairChar]) -> Vec<PairChar> {
         let mut pair_vec: Vec<PairChar> = Vec::new();
         for pairchar in pairchar_slice {
             if !pairchar.is_blank() {
-                pair_vec.push(pairchar.clone());
+                pair_vec.push(*pairchar);
             }
         }
         pair_vec
     }
 
     pub fn print(&self) {
+        print!("{}", self.to_text());
+    }
+
+    // the grid in the text format read by parse, one row of tiles per line
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
         for y in 0..(self.depth) {
             for x in 0..(self.width) {
-                print!("{} ", self.columns[x][y].decode());
+                text.push_str(&self.columns[x][y].decode());
+                text.push(' ');
             }
-            println!();
+            text.push('\n');
         }
+        text
     }
 
-    // recursion function for populate_grid
+    // entry point for the recursive search: place word at the next layer, then fill out the
+    // remaining layers of the grid
     pub fn populate_layer(
         &mut self,
         word: &PairString,
-        depth: usize,
         horizontal_index: &BigramIndexTree,
         vertical_index: &BigramIndexTree,
         continue_running: Option<&AtomicBool>,
     ) -> bool {
-        // check whether to continue loop (only bother for the two highest levels)
-        if depth <= 1
-            && continue_running.is_some()
-            && !continue_running.unwrap().load(Ordering::Relaxed)
-        {
+        self.populate_layer_with(word, horizontal_index, vertical_index, continue_running, &mut |_| true)
+    }
+
+    // as populate_layer, but each completed grid is offered to on_complete, and the search
+    // carries on to the next solution if it returns false
+    pub fn populate_layer_with(
+        &mut self,
+        word: &PairString,
+        horizontal_index: &BigramIndexTree,
+        vertical_index: &BigramIndexTree,
+        continue_running: Option<&AtomicBool>,
+        on_complete: &mut dyn FnMut(&PuzzleGrid) -> bool,
+    ) -> bool {
+        if !self.fits_layer(word, self.next_layer) {
+            return false;
+        }
+
+        // position a cursor at the end of each of the existing columns
+        let mut column_cursors: Vec<IndexCursor> = Vec::new();
+        for column in self.get_columns() {
+            let mut cursor = vertical_index.cursor();
+            for pairchar in column {
+                cursor.descend(*pairchar);
+            }
+            column_cursors.push(cursor);
+        }
+
+        self.populate_layer_with_cursors(
+            word,
+            horizontal_index,
+            &mut column_cursors,
+            continue_running,
+            on_complete,
+        )
+    }
+
+    // recursion function for populate_grid, the column cursors track each column's position in
+    // the vertical index and move in step with the layers of the grid
+    fn populate_layer_with_cursors(
+        &mut self,
+        word: &PairString,
+        horizontal_index: &BigramIndexTree,
+        column_cursors: &mut [IndexCursor],
+        continue_running: Option<&AtomicBool>,
+        on_complete: &mut dyn FnMut(&PuzzleGrid) -> bool,
+    ) -> bool {
+        // check whether to continue loop, at every level so that a search stopped from outside
+        // (a cancelled job) or running out of time winds up promptly
+        if continue_running.is_some_and(|x| !x.load(Ordering::Relaxed)) || self.is_past_deadline() {
+            return false;
+        }
+
+        // the filters can't check blanks within the same layer, so check them here
+        if !self.fits_symmetry(word) || !self.fits_blank_rules(word) || !self.fits_repeats(word) {
             return false;
         }
 
         self.add_layer(word);
 
         if self.is_complete() {
-            // if we've found a duplicate discard this solution, continue checking possibles
-            if self.has_duplicates() {
+            // if we've found a duplicate (or the caller turns the grid down) discard this
+            // solution, continue checking possibles
+            if self.breaks_repeats() || !on_complete(self) {
                 self.remove_layer();
                 return false;
             }
@@ -155,30 +873,37 @@ impl PuzzleGrid {
             return true;
         };
 
-        let column_stems = self.get_columns();
-
-        let possible_pairchars = vertical_index.get_possible_pairchars(column_stems);
-        let candidate_words = BigramIndexTree::get_candidate_words(horizontal_index, &possible_pairchars);
-
-        // recurse down if we have candidate words to check
-        if let Some(v) = candidate_words {
-            let word_iterator = WordIterator::new(v);
-            for word in word_iterator {
-                if self.populate_layer(
-                    &word,
-                    depth + 1,
-                    horizontal_index,
-                    vertical_index,
-                    continue_running,
-                ) {
-                    return true;
-                }
+        for (cursor, pairchar) in column_cursors.iter_mut().zip(word.slice()) {
+            cursor.descend(*pairchar);
+        }
+
+        let mut possible_pairchars: Vec<HashSet<PairChar>> =
+            column_cursors.iter().map(IndexCursor::possible_pairchars).collect();
+        self.restrict_to_constraints(self.next_layer, &mut possible_pairchars);
+        self.restrict_to_symmetry(&mut possible_pairchars);
+        self.restrict_to_blank_rules(&mut possible_pairchars);
+        self.restrict_to_diagonals(horizontal_index, &mut possible_pairchars);
+        self.restrict_to_repeats(&mut possible_pairchars);
+
+        // recurse down for each candidate word in turn, only generating the next candidate if
+        // the previous one didn't lead to a solution
+        for word in BigramIndexTree::get_candidate_words(horizontal_index, &possible_pairchars) {
+            if self.populate_layer_with_cursors(
+                &word,
+                horizontal_index,
+                column_cursors,
+                continue_running,
+                on_complete,
+            ) {
+                return true;
             }
-        };
+        }
 
+        for cursor in column_cursors.iter_mut() {
+            cursor.ascend();
+        }
         self.remove_layer();
 
         false
     }
 }
-
@@ -0,0 +1,104 @@
+use super::clues;
+use super::puzzlegrid::{Direction, PuzzleGrid};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+const CELL_SIZE: usize = 40;
+const MARGIN: usize = 2;
+
+// draw a grid as an svg, each pair tile centred in its cell, blanks shaded and the cells
+// outside a shape left out
+// without answers the letter cells are left empty, for a puzzle to be solved
+pub fn render_svg(grid: &PuzzleGrid, show_answers: bool, show_numbers: bool) -> String {
+    let numbers: HashMap<(usize, usize), usize> = if show_numbers {
+        grid.entries().iter().map(|x| ((x.x, x.y), x.number)).collect()
+    } else {
+        HashMap::new()
+    };
+
+    let width = grid.width() * CELL_SIZE + 2 * MARGIN;
+    let height = grid.depth() * CELL_SIZE + 2 * MARGIN;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+        width, height, width, height
+    );
+    svg.push_str("<style>rect{stroke:#000;stroke-width:1}.letter{fill:#fff}.blank{fill:#222}");
+    svg.push_str(".tile{font:18px sans-serif;text-anchor:middle}.number{font:10px sans-serif}</style>\n");
+
+    for y in 0..grid.depth() {
+        for x in 0..grid.width() {
+            let tile = grid.get_tile(x, y);
+            if tile.is_outside() {
+                continue;
+            }
+
+            let left = MARGIN + x * CELL_SIZE;
+            let top = MARGIN + y * CELL_SIZE;
+            let class = if tile.is_blank() { "blank" } else { "letter" };
+            writeln!(
+                svg,
+                "<rect class=\"{}\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>",
+                class, left, top, CELL_SIZE, CELL_SIZE
+            )
+            .unwrap();
+
+            if let Some(number) = numbers.get(&(x, y)) {
+                writeln!(svg, "<text class=\"number\" x=\"{}\" y=\"{}\">{}</text>", left + 2, top + 10, number).unwrap();
+            }
+            if show_answers && !tile.is_blank() && !tile.is_wildcard() {
+                writeln!(
+                    svg,
+                    "<text class=\"tile\" x=\"{}\" y=\"{}\">{}</text>",
+                    left + CELL_SIZE / 2,
+                    top + CELL_SIZE * 2 / 3,
+                    tile.decode().to_uppercase()
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// a self contained page showing the numbered grid to solve and its clues (if any), with the
+// answers hidden away in a second view underneath
+pub fn render_html(grid: &PuzzleGrid, title: &str, clues: Option<&HashMap<String, String>>) -> String {
+    let title = escape_html(title);
+    let mut html = String::new();
+
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    writeln!(html, "<title>{}</title>", title).unwrap();
+    html.push_str("<style>body{font-family:sans-serif;margin:2em}details{margin-top:2em}</style>\n");
+    html.push_str("</head>\n<body>\n");
+    writeln!(html, "<h1>{}</h1>", title).unwrap();
+    html.push_str("<section class=\"solve\">\n");
+    html.push_str(&render_svg(grid, false, true));
+    if let Some(clues) = clues {
+        for direction in &[Direction::Across, Direction::Down] {
+            writeln!(html, "<h2>{}</h2>\n<ol class=\"{}\">", direction_title(*direction), direction).unwrap();
+            for entry in grid.entries().iter().filter(|x| x.direction == *direction) {
+                let clue = clues::clue_for(entry, Some(clues)).unwrap_or("");
+                writeln!(html, "<li value=\"{}\">{}</li>", entry.number, escape_html(clue)).unwrap();
+            }
+            html.push_str("</ol>\n");
+        }
+    }
+    html.push_str("</section>\n<details class=\"answers\">\n<summary>Answers</summary>\n");
+    html.push_str(&render_svg(grid, true, true));
+    html.push_str("</details>\n</body>\n</html>\n");
+
+    html
+}
+
+fn direction_title(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Across => "Across",
+        Direction::Down => "Down",
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
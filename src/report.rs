@@ -0,0 +1,109 @@
+use super::clues;
+use super::puzzlecube::PuzzleCube;
+use super::puzzlegrid::PuzzleGrid;
+use super::types::PairString;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+// Settings and timings for a single solver run, written out alongside the solution so that
+// scripts get everything they need from one document
+pub struct RunReport {
+    width: usize,
+    depth: usize,
+    max_blanks: usize,
+    dictionary_fingerprint: Option<String>,
+    clues: Option<HashMap<String, String>>,
+    timings: Vec<(String, Duration)>,
+    stage_start: Instant,
+}
+
+impl RunReport {
+    pub fn new(width: usize, depth: usize, max_blanks: usize) -> RunReport {
+        RunReport {
+            width,
+            depth,
+            max_blanks,
+            dictionary_fingerprint: None,
+            clues: None,
+            timings: Vec::new(),
+            stage_start: Instant::now(),
+        }
+    }
+
+    pub fn set_dictionary_fingerprint(&mut self, fingerprint: String) {
+        self.dictionary_fingerprint = Some(fingerprint);
+    }
+
+    // clues to attach to the entries of the solution, looked up by answer
+    pub fn set_clues(&mut self, clues: HashMap<String, String>) {
+        self.clues = Some(clues);
+    }
+
+    // record the time taken since the previous stage finished (or the report was created)
+    pub fn finish_stage(&mut self, stage: &str) {
+        let now = Instant::now();
+        self.timings.push((stage.to_string(), now - self.stage_start));
+        self.stage_start = now;
+    }
+
+    pub fn to_json(&self, solution: Option<&PuzzleGrid>) -> Value {
+        let words = |words: Vec<PairString>| words.iter().map(PairString::decode).collect::<Vec<String>>();
+
+        let mut report = self.run_json(solution.is_some());
+        report["grid"] = json!(solution.map(grid_tiles));
+        report["across"] = json!(solution.map(|grid| words(grid.across_words())));
+        report["down"] = json!(solution.map(|grid| words(grid.down_words())));
+
+        // entries without a clue are listed with a null clue, for editors to fill in
+        if let (Some(clues), Some(grid)) = (&self.clues, solution) {
+            report["clues"] = grid
+                .entries()
+                .iter()
+                .map(|entry| {
+                    json!({
+                        "number": entry.number,
+                        "direction": entry.direction.to_string(),
+                        "clue": clues::clue_for(entry, Some(clues)),
+                        "answer": entry.word.decode(),
+                    })
+                })
+                .collect();
+        }
+
+        report
+    }
+
+    // the report for a word cube, with the tiles of each slice from the bottom slice up
+    pub fn cube_to_json(&self, height: usize, solution: Option<&PuzzleCube>) -> Value {
+        let mut report = self.run_json(solution.is_some());
+        report["height"] = json!(height);
+        report["slices"] = json!(solution.map(|cube| cube.get_slices().iter().map(grid_tiles).collect::<Vec<_>>()));
+        report
+    }
+
+    // the settings, timings and outcome shared by every kind of run
+    fn run_json(&self, solved: bool) -> Value {
+        let timings: serde_json::Map<String, Value> = self
+            .timings
+            .iter()
+            .map(|(stage, time)| (stage.clone(), json!(time.as_secs_f64() * 1000.0)))
+            .collect();
+
+        json!({
+            "width": self.width,
+            "depth": self.depth,
+            "maxblanks": self.max_blanks,
+            "dictionary": self.dictionary_fingerprint,
+            "timings_ms": timings,
+            "outcome": if solved { "solved" } else { "no_solution" },
+        })
+    }
+}
+
+// the tiles of a grid, row by row
+fn grid_tiles(grid: &PuzzleGrid) -> Vec<Vec<String>> {
+    (0..grid.depth())
+        .map(|y| (0..grid.width()).map(|x| grid.get_tile(x, y).decode()).collect())
+        .collect()
+}
@@ -0,0 +1,158 @@
+use super::service::{self, Service};
+use serde_json::{json, Value};
+use std::io::{self, BufRead, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+// answer newline delimited json requests, one per line, with one response line each (even
+// for a request which panics), matched up by id since loading can answer out of order
+//   {"id": 1, "method": "candidates", "params": {"pattern": "di ?? ?? ??"}}
+//   {"id": 1, "result": {"candidates": [...]}} or {"id": 1, "error": "..."}
+// solves run in the background, sending "progress" notifications (without an id) while they
+// run and a "finished" notification at the end, and can be stopped with "cancel"
+// when the input ends any running solves are cancelled, and their last notifications sent
+// the session also ends once the output can't be written to (the editor has gone away)
+pub fn run_rpc<W: Write + Send + 'static>(service: Arc<Service>, input: impl BufRead, output: W) {
+    let output = Arc::new(Output {
+        writer: Mutex::new(output),
+        closed: AtomicBool::new(false),
+    });
+    let mut jobs: Vec<(u64, thread::JoinHandle<()>)> = Vec::new();
+    let mut workers: Vec<thread::JoinHandle<()>> = Vec::new();
+
+    for line in input.lines().map_while(Result::ok) {
+        if output.is_closed() {
+            break;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let request: Value = match serde_json::from_str(&line) {
+            Ok(v) => v,
+            Err(e) => {
+                output.send(&json!({ "id": null, "error": format!("Invalid json: {}", e) }));
+                continue;
+            }
+        };
+
+        match request["method"].as_str().unwrap_or("") {
+            // reading a dictionary or building an index can take a while, so they're answered
+            // from a worker, leaving the session free for status and cancel requests meanwhile
+            "load_dictionary" | "build_index" => {
+                let (service, output) = (service.clone(), output.clone());
+                workers.push(thread::spawn(move || respond(&output, &request, dispatch(&service, &request))));
+            }
+            method => {
+                let result = dispatch(&service, &request);
+                let job = result.as_ref().ok().and_then(|x| x["job"].as_u64()).filter(|_| method == "solve");
+                respond(&output, &request, result);
+                if let Some(job_id) = job {
+                    jobs.push((job_id, watch_job(service.clone(), output.clone(), job_id)));
+                }
+            }
+        }
+    }
+
+    for worker in workers {
+        if worker.join().is_err() {
+            eprintln!("Lost the response to a request");
+        }
+    }
+    for (job_id, watcher) in jobs {
+        service.cancel_job(job_id);
+        if watcher.join().is_err() {
+            eprintln!("Lost the notifications for job {}", job_id);
+        }
+    }
+}
+
+// run a request against the service, turning a panic into an error so that one bad request
+// can't end the session
+fn dispatch(service: &Service, request: &Value) -> Result<Value, String> {
+    let params = &request["params"];
+    let method = request["method"].as_str().unwrap_or("");
+
+    panic::catch_unwind(AssertUnwindSafe(|| match method {
+        "load_dictionary" => service.load_dictionary(params),
+        "build_index" => service.build_index(params),
+        "candidates" => service.candidates(params),
+        "validate" => service.validate(params),
+        "solve" => service.start_solve(params),
+        "status" => job_id(params).and_then(|x| service.job_status(x).ok_or(no_such_job(x))),
+        "cancel" => job_id(params).and_then(|x| service.cancel_job(x).ok_or(no_such_job(x))),
+        method => Err(format!("Unknown method {}", method)),
+    }))
+    .unwrap_or_else(|e| Err(service::panic_message(e.as_ref())))
+}
+
+// the response line for a request, carrying its id
+fn respond<W: Write>(output: &Output<W>, request: &Value, result: Result<Value, String>) {
+    match result {
+        Ok(v) => output.send(&json!({ "id": request["id"], "result": v })),
+        Err(e) => output.send(&json!({ "id": request["id"], "error": e })),
+    };
+}
+
+// poll a solve until it stops, sending its progress along the way, or until the output closes
+// or the job has been forgotten
+fn watch_job<W: Write + Send + 'static>(
+    service: Arc<Service>,
+    output: Arc<Output<W>>,
+    job_id: u64,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || loop {
+        thread::sleep(PROGRESS_INTERVAL);
+        let status = match service.job_status(job_id) {
+            Some(v) => v,
+            None => return,
+        };
+        if status["status"] != "running" {
+            output.send(&json!({ "method": "finished", "params": status }));
+            return;
+        }
+        if !output.send(&json!({ "method": "progress", "params": status })) {
+            return;
+        }
+    })
+}
+
+// Where the responses and notifications go, shared with the job watchers
+// the first failed write marks it closed, which ends the session
+struct Output<W> {
+    writer: Mutex<W>,
+    closed: AtomicBool,
+}
+
+impl<W: Write> Output<W> {
+    // write a message on a line of its own, false if the output has closed
+    fn send(&self, message: &Value) -> bool {
+        if self.is_closed() {
+            return false;
+        }
+
+        let mut writer = self.writer.lock().unwrap();
+        let written: io::Result<()> = writeln!(writer, "{}", message).and_then(|_| writer.flush());
+        if written.is_err() {
+            self.closed.store(true, Ordering::Relaxed);
+        }
+        written.is_ok()
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Relaxed)
+    }
+}
+
+fn job_id(params: &Value) -> Result<u64, String> {
+    params["job"].as_u64().ok_or_else(|| "Missing job".to_string())
+}
+
+fn no_such_job(job_id: u64) -> String {
+    format!("No such job {}", job_id)
+}
@@ -0,0 +1,133 @@
+use super::service::Service;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+// requests are small json documents, anything bigger is turned away before it's read
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+// how long to wait on a client which stops sending part way through a request
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+// answer http requests on a listener (bound to localhost by the caller), one thread per
+// connection, with every connection sharing the service's loaded indices and jobs
+//   POST /solve, POST /validate, POST /candidates with a json body
+//   GET /jobs/<id> to poll a solve, DELETE /jobs/<id> to cancel it
+pub fn serve(listener: TcpListener, service: Arc<Service>) {
+    for stream in listener.incoming().flatten() {
+        let service = service.clone();
+        thread::spawn(move || {
+            if let Err(e) = handle_connection(stream, &service) {
+                eprintln!("Failed to answer request: {}", e);
+            }
+        });
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, service: &Service) -> Result<(), String> {
+    stream.set_read_timeout(Some(READ_TIMEOUT)).map_err(|e| e.to_string())?;
+    let (status, body) = match read_request(&stream) {
+        Ok((method, path, body)) => route(service, &method, &path, &body),
+        Err((status, e)) => (status, json!({ "error": e })),
+    };
+
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason_phrase(status),
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).map_err(|e| e.to_string())
+}
+
+// the method, path and body of a request, the body being as long as its Content-Length
+// failures come with the status code to answer them with
+fn read_request(stream: &TcpStream) -> Result<(String, String, String), (u16, String)> {
+    let bad_request = |e: String| (400, e);
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).map_err(|e| bad_request(e.to_string()))?;
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next()) {
+        (Some(m), Some(p)) => (m.to_string(), p.to_string()),
+        _ => return Err(bad_request("Invalid request line".to_string())),
+    };
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).map_err(|e| bad_request(e.to_string()))?;
+        if header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse::<usize>().map_err(|e| bad_request(e.to_string()))?;
+            }
+        }
+    }
+    if content_length > MAX_BODY_SIZE {
+        return Err((413, format!("Request bodies can be at most {} bytes", MAX_BODY_SIZE)));
+    }
+
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body).map_err(|e| bad_request(e.to_string()))?;
+    let body = String::from_utf8(body).map_err(|e| bad_request(e.to_string()))?;
+
+    Ok((method, path, body))
+}
+
+// the status code and json response for a request
+fn route(service: &Service, method: &str, path: &str, body: &str) -> (u16, Value) {
+    let not_found = (404, json!({ "error": format!("No such resource {}", path) }));
+
+    if let Some(job_id) = path.strip_prefix("/jobs/") {
+        let job_id = match job_id.parse::<u64>() {
+            Ok(v) => v,
+            Err(_) => return not_found,
+        };
+        let job = match method {
+            "GET" => service.job_status(job_id),
+            "DELETE" => service.cancel_job(job_id),
+            _ => return (405, json!({ "error": format!("{} isn't allowed on {}", method, path) })),
+        };
+        return job.map_or(not_found, |x| (200, x));
+    }
+
+    let handler = match path {
+        "/solve" => Service::start_solve,
+        "/validate" => Service::validate,
+        "/candidates" => Service::candidates,
+        _ => return not_found,
+    };
+    if method != "POST" {
+        return (405, json!({ "error": format!("{} isn't allowed on {}", method, path) }));
+    }
+
+    let request: Value = match serde_json::from_str(body) {
+        Ok(v) => v,
+        Err(e) => return (400, json!({ "error": format!("Invalid json: {}", e) })),
+    };
+    match handler(service, &request) {
+        Ok(v) if path == "/solve" => (202, v),
+        Ok(v) => (200, v),
+        Err(e) => (400, json!({ "error": e })),
+    }
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        _ => "",
+    }
+}
@@ -0,0 +1,348 @@
+use super::bigramindex::BigramIndexTree;
+use super::puzzlegrid::PuzzleGrid;
+use super::types::{PairChar, PairString};
+use super::validate;
+use super::wordstore::{LineMode, WordStore, Wrap};
+use serde_json::{json, Value};
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const DEFAULT_CANDIDATE_LIMIT: usize = 100;
+const DEFAULT_JOB_TTL: Duration = Duration::from_secs(600);
+
+// Where a solve job has got to
+#[derive(Clone)]
+pub enum JobStatus {
+    Running,
+    Solved(PuzzleGrid),
+    NoSolution,
+    Cancelled,
+    TimedOut,
+    Failed(String),
+}
+
+// A solve running on its own thread, stopped early by clearing continue_running
+// progress is counted in start words for the top row, once they've been found
+struct Job {
+    status: Mutex<JobStatus>,
+    finished_at: Mutex<Option<Instant>>,
+    continue_running: AtomicBool,
+    top_words: AtomicUsize,
+    top_words_tried: AtomicUsize,
+}
+
+impl Job {
+    // finish a running job, recording how; a job which has already finished is left alone
+    fn finish(&self, outcome: JobStatus) {
+        let mut status = self.status.lock().unwrap();
+        if let JobStatus::Running = *status {
+            *status = outcome;
+            *self.finished_at.lock().unwrap() = Some(Instant::now());
+            self.continue_running.store(false, Ordering::Relaxed);
+        }
+    }
+
+    fn has_expired(&self, ttl: Duration) -> bool {
+        self.finished_at.lock().unwrap().is_some_and(|x| x.elapsed() >= ttl)
+    }
+}
+
+// a word store or index, built once by whichever request gets to it first
+type Slot<T> = Arc<OnceLock<Arc<T>>>;
+
+// Word stores and indices read in so far, each in its own slot so that building one only holds
+// up the requests waiting for that one, and not those using what's already loaded
+#[derive(Default)]
+struct Loaded {
+    word_stores: Mutex<HashMap<String, Slot<WordStore>>>,
+    indices: Mutex<HashMap<(String, usize, usize), Slot<BigramIndexTree>>>,
+}
+
+impl Loaded {
+    // a dictionary can be used if it's already been read, or is a file to read
+    fn check_dictionary(&self, dictionary: &str) -> Result<(), String> {
+        let word_stores = self.word_stores.lock().unwrap();
+        if word_stores.get(dictionary).is_some_and(|x| x.get().is_some()) || Path::new(dictionary).is_file() {
+            Ok(())
+        } else {
+            Err(format!("Dictionary {} not found", dictionary))
+        }
+    }
+
+    fn word_store(&self, dictionary: &str) -> Result<Arc<WordStore>, String> {
+        self.check_dictionary(dictionary)?;
+        let slot = self.word_stores.lock().unwrap().entry(dictionary.to_string()).or_default().clone();
+        Ok(slot.get_or_init(|| Arc::new(super::generate_wordstore(dictionary))).clone())
+    }
+
+    fn index(&self, dictionary: &str, size: usize, max_blanks: usize) -> Result<Arc<BigramIndexTree>, String> {
+        let word_store = self.word_store(dictionary)?;
+        if size == 0 || size > word_store.max_length() {
+            return Err(format!("Lines must be from 1 to {} tiles long, not {}", word_store.max_length(), size));
+        }
+        let key = (dictionary.to_string(), size, max_blanks);
+        let slot = self.indices.lock().unwrap().entry(key).or_default().clone();
+        Ok(slot.get_or_init(|| Arc::new(BigramIndexTree::build(size, &word_store, max_blanks))).clone())
+    }
+}
+
+// Word stores and indices kept loaded between requests, so that each request only pays for
+// building what it hasn't seen before, and the solve jobs started from them
+// finished jobs are kept for job_ttl, for their results to be collected, and then dropped
+// requests and responses are json, for the front ends (http, rpc) to pass straight through
+pub struct Service {
+    default_dictionary: String,
+    loaded: Arc<Loaded>,
+    jobs: Mutex<HashMap<u64, Arc<Job>>>,
+    next_job: AtomicU64,
+    job_ttl: Duration,
+}
+
+impl Service {
+    pub fn new(default_dictionary: &str) -> Service {
+        Service {
+            default_dictionary: default_dictionary.to_string(),
+            loaded: Arc::new(Loaded::default()),
+            jobs: Mutex::new(HashMap::new()),
+            next_job: AtomicU64::new(1),
+            job_ttl: DEFAULT_JOB_TTL,
+        }
+    }
+
+    // how long a finished job is kept before it's forgotten
+    pub fn set_job_ttl(&mut self, job_ttl: Duration) {
+        self.job_ttl = job_ttl;
+    }
+
+    // the dictionary named by a request, or the default one
+    fn dictionary<'a>(&'a self, request: &'a Value) -> &'a str {
+        request["dictionary"].as_str().unwrap_or(&self.default_dictionary)
+    }
+
+    // read a dictionary into a word store, the first time it's asked for
+    pub fn word_store(&self, dictionary: &str) -> Result<Arc<WordStore>, String> {
+        self.loaded.word_store(dictionary)
+    }
+
+    // read in a dictionary ahead of the requests which use it, eg. {"dictionary": "words"}
+    pub fn load_dictionary(&self, request: &Value) -> Result<Value, String> {
+        let word_store = self.word_store(self.dictionary(request))?;
+        Ok(json!({ "dictionary": self.dictionary(request), "fingerprint": word_store.fingerprint() }))
+    }
+
+    // build an index ahead of the requests which use it, eg. {"size": 4, "maxblanks": 0}
+    // an index which is already loaded is just reported on
+    pub fn build_index(&self, request: &Value) -> Result<Value, String> {
+        let size = optional_usize(request, "size")?.ok_or("Missing size")?;
+        let max_blanks = optional_usize(request, "maxblanks")?.unwrap_or(0);
+        let index = self.index(self.dictionary(request), size, max_blanks)?;
+        Ok(json!({ "size": size, "maxblanks": max_blanks, "entries": index.entry_count() }))
+    }
+
+    // the index of lines of a size from a dictionary, building it the first time
+    pub fn index(&self, dictionary: &str, size: usize, max_blanks: usize) -> Result<Arc<BigramIndexTree>, String> {
+        self.loaded.index(dictionary, size, max_blanks)
+    }
+
+    // words which fit a partial line of tiles, eg. {"pattern": "di ?? ru ??"}
+    pub fn candidates(&self, request: &Value) -> Result<Value, String> {
+        let pattern = request["pattern"].as_str().ok_or("Missing pattern")?;
+        let tiles = pattern
+            .split_whitespace()
+            .map(PairChar::parse)
+            .collect::<Result<Vec<PairChar>, String>>()?;
+        if tiles.is_empty() {
+            return Err("Empty pattern".to_string());
+        }
+        let max_blanks = optional_usize(request, "maxblanks")?.unwrap_or(0);
+        let limit = optional_usize(request, "limit")?.unwrap_or(DEFAULT_CANDIDATE_LIMIT);
+
+        let index = self.index(self.dictionary(request), tiles.len(), max_blanks)?;
+        let filters: Vec<HashSet<PairChar>> = tiles
+            .iter()
+            .map(|x| {
+                if x.is_wildcard() {
+                    PairChar::all_tiles()
+                } else {
+                    std::iter::once(*x).collect()
+                }
+            })
+            .collect();
+        let candidates: Vec<String> = BigramIndexTree::get_candidate_words(&index, &filters)
+            .take(limit)
+            .map(|x| x.decode())
+            .collect();
+
+        Ok(json!({ "candidates": candidates }))
+    }
+
+    // check a grid in the text format, eg. {"grid": "di st ru st\n...", "maxblanks": 0}
+    pub fn validate(&self, request: &Value) -> Result<Value, String> {
+        let grid = PuzzleGrid::parse(request["grid"].as_str().ok_or("Missing grid")?)?;
+        let max_blanks = optional_usize(request, "maxblanks")?.unwrap_or(0);
+        let word_store = self.word_store(self.dictionary(request))?;
+
+        let errors: Vec<String> = validate::validate_grid(&grid, &word_store, max_blanks, LineMode::Joined, Wrap::Flat)
+            .iter()
+            .map(|x| x.to_string())
+            .collect();
+        Ok(json!({ "valid": errors.is_empty(), "errors": errors }))
+    }
+
+    // start solving a grid on another thread, returning the job to poll straight away, with any
+    // indices it needs built on that thread
+    // the grid is either {"width": w, "depth": d} or a partly filled {"seed": "..."}, with an
+    // optional "timeout_ms" after which the job gives up
+    pub fn start_solve(&self, request: &Value) -> Result<Value, String> {
+        let seed_grid = match request["seed"].as_str() {
+            Some(v) => PuzzleGrid::parse(v)?,
+            None => {
+                let width = optional_usize(request, "width")?.ok_or("Missing width")?;
+                let depth = optional_usize(request, "depth")?.ok_or("Missing depth")?;
+                if width == 0 || depth == 0 {
+                    return Err("The grid needs a width and depth of at least 1".to_string());
+                }
+                PuzzleGrid::new(width, depth)
+            }
+        };
+        let max_blanks = optional_usize(request, "maxblanks")?.unwrap_or(0);
+        let timeout = optional_usize(request, "timeout_ms")?.map(|x| Duration::from_millis(x as u64));
+
+        let dictionary = self.dictionary(request).to_string();
+        self.loaded.check_dictionary(&dictionary)?;
+
+        self.prune_jobs();
+        let job = Arc::new(Job {
+            status: Mutex::new(JobStatus::Running),
+            finished_at: Mutex::new(None),
+            continue_running: AtomicBool::new(true),
+            top_words: AtomicUsize::new(0),
+            top_words_tried: AtomicUsize::new(0),
+        });
+        let job_id = self.next_job.fetch_add(1, Ordering::Relaxed);
+        self.jobs.lock().unwrap().insert(job_id, job.clone());
+
+        // the solver checks the deadline as it goes, so nothing has to wake up to stop it
+        let mut seed_grid = seed_grid;
+        seed_grid.set_deadline(timeout.map(|x| Instant::now() + x));
+
+        let solve_job = job.clone();
+        let loaded = self.loaded.clone();
+        thread::spawn(move || {
+            // a panic building the indices or solving still finishes the job, rather than
+            // leaving it running for good
+            let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+                solve(&loaded, &dictionary, &seed_grid, max_blanks, &solve_job)
+            }));
+            solve_job.finish(outcome.unwrap_or_else(|e| JobStatus::Failed(panic_message(e.as_ref()))));
+        });
+
+        Ok(job_json(job_id, &job))
+    }
+
+    // the status of a job, with the grid if it's been solved, None if there's no such job
+    pub fn job_status(&self, job_id: u64) -> Option<Value> {
+        self.prune_jobs();
+        let job = self.jobs.lock().unwrap().get(&job_id).cloned()?;
+        Some(job_json(job_id, &job))
+    }
+
+    // stop a running job through the solver's stop flag
+    pub fn cancel_job(&self, job_id: u64) -> Option<Value> {
+        self.prune_jobs();
+        let job = self.jobs.lock().unwrap().get(&job_id).cloned()?;
+        job.finish(JobStatus::Cancelled);
+        Some(job_json(job_id, &job))
+    }
+
+    // forget the jobs which finished more than job_ttl ago
+    fn prune_jobs(&self) {
+        self.jobs.lock().unwrap().retain(|_, job| !job.has_expired(self.job_ttl));
+    }
+}
+
+// build the indices for a job and run its solve, returning how it finished
+// a job which was cancelled meanwhile keeps that as its outcome, as finish leaves it alone
+fn solve(loaded: &Loaded, dictionary: &str, seed_grid: &PuzzleGrid, max_blanks: usize, job: &Job) -> JobStatus {
+    let indices = loaded.index(dictionary, seed_grid.width(), max_blanks).and_then(|horizontal_index| {
+        Ok((horizontal_index, loaded.index(dictionary, seed_grid.depth(), max_blanks)?))
+    });
+    let (horizontal_index, vertical_index) = match indices {
+        Ok(v) => v,
+        Err(e) => return JobStatus::Failed(e),
+    };
+
+    let top_start_words = super::generate_seeded_top_words(seed_grid, &horizontal_index, &vertical_index);
+    job.top_words.store(top_start_words.len(), Ordering::Relaxed);
+    let solution = super::populate_seeded_grid_until(
+        seed_grid,
+        &top_start_words,
+        &horizontal_index,
+        &vertical_index,
+        &job.continue_running,
+        Some(&job.top_words_tried),
+    );
+
+    match solution {
+        Some(v) => JobStatus::Solved(v),
+        None if seed_grid.is_past_deadline() => JobStatus::TimedOut,
+        None => JobStatus::NoSolution,
+    }
+}
+
+// the message a panic was raised with, for reporting it as an error
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown error");
+    format!("Internal error: {}", message)
+}
+
+fn job_json(job_id: u64, job: &Job) -> Value {
+    let status = job.status.lock().unwrap().clone();
+    let status_name = match &status {
+        JobStatus::Running => "running",
+        JobStatus::Solved(_) => "solved",
+        JobStatus::NoSolution => "no_solution",
+        JobStatus::Cancelled => "cancelled",
+        JobStatus::TimedOut => "timed_out",
+        JobStatus::Failed(_) => "failed",
+    };
+
+    let mut job_json = json!({
+        "job": job_id,
+        "status": status_name,
+        "top_words": job.top_words.load(Ordering::Relaxed),
+        "top_words_tried": job.top_words_tried.load(Ordering::Relaxed),
+    });
+    match status {
+        JobStatus::Solved(grid) => {
+            let words = |words: Vec<PairString>| -> Vec<String> { words.iter().map(|x| x.decode()).collect() };
+            job_json["grid"] = json!(grid.to_text());
+            job_json["across"] = json!(words(grid.across_words()));
+            job_json["down"] = json!(words(grid.down_words()));
+        }
+        JobStatus::Failed(e) => job_json["error"] = json!(e),
+        _ => (),
+    }
+    job_json
+}
+
+// a number from a request which can be left out, but must be a whole number if it's there
+fn optional_usize(request: &Value, key: &str) -> Result<Option<usize>, String> {
+    match &request[key] {
+        Value::Null => Ok(None),
+        v => v
+            .as_u64()
+            .map(|x| Some(x as usize))
+            .ok_or(format!("Expected a whole number for {}", key)),
+    }
+}
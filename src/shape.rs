@@ -0,0 +1,291 @@
+use super::bigramindex::{BigramIndexTree, IndexCache, IndexCursor};
+use super::puzzlegrid::PuzzleGrid;
+use super::types::{PairChar, PairString};
+use super::wordstore::LineMode;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// A straight line of cells within a shape, running across or down from (x, y)
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Run {
+    pub x: usize,
+    pub y: usize,
+    pub length: usize,
+}
+
+// A mask marking which cells of a bounding box belong to the puzzle ("."), and which are
+// outside of it ("x"), for diamond, plus and staircase shaped grids
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Shape {
+    rows: Vec<Vec<bool>>,
+    width: usize,
+    depth: usize,
+}
+
+impl Shape {
+    // read a mask with one line per row, short rows are padded with cells outside the shape
+    pub fn parse(shape_text: &str) -> Result<Shape, String> {
+        let mut rows: Vec<Vec<bool>> = Vec::new();
+
+        for (line_number, line) in shape_text.lines().enumerate() {
+            let mut row: Vec<bool> = Vec::new();
+            for cell in line.chars().filter(|x| !x.is_whitespace()) {
+                match cell {
+                    '.' => row.push(true),
+                    'x' => row.push(false),
+                    _ => {
+                        return Err(format!("Invalid shape cell '{}' on line {}", cell, line_number + 1));
+                    }
+                }
+            }
+
+            if !row.is_empty() {
+                rows.push(row);
+            }
+        }
+
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if width == 0 {
+            return Err("No cells found in shape".to_string());
+        }
+        for row in rows.iter_mut() {
+            row.resize(width, false);
+        }
+
+        let shape = Shape {
+            width,
+            depth: rows.len(),
+            rows,
+        };
+
+        // every cell needs to be part of a line in at least one direction
+        for y in 0..shape.depth {
+            for x in 0..shape.width {
+                if shape.is_inside(x, y) && shape.run_across(x, y).length == 1 && shape.run_down(x, y).length == 1 {
+                    return Err(format!("Cell {} {} isn't part of any line", x, y));
+                }
+            }
+        }
+
+        Ok(shape)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn is_inside(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.depth && self.rows[y][x]
+    }
+
+    // the horizontal run of cells through (x, y)
+    fn run_across(&self, x: usize, y: usize) -> Run {
+        let start = (0..=x).rev().take_while(|i| self.is_inside(*i, y)).last().unwrap_or(x);
+        let length = (start..self.width).take_while(|i| self.is_inside(*i, y)).count();
+
+        Run { x: start, y, length }
+    }
+
+    // the vertical run of cells through (x, y)
+    fn run_down(&self, x: usize, y: usize) -> Run {
+        let start = (0..=y).rev().take_while(|i| self.is_inside(x, *i)).last().unwrap_or(y);
+        let length = (start..self.depth).take_while(|i| self.is_inside(x, *i)).count();
+
+        Run { x, y: start, length }
+    }
+
+    // every horizontal run in the shape, in reading order (including single cells)
+    pub fn runs_across(&self) -> Vec<Run> {
+        let mut runs: Vec<Run> = Vec::new();
+        for y in 0..self.depth {
+            for x in 0..self.width {
+                if self.is_inside(x, y) && (x == 0 || !self.is_inside(x - 1, y)) {
+                    runs.push(self.run_across(x, y));
+                }
+            }
+        }
+        runs
+    }
+
+    // every vertical run in the shape, column by column (including single cells)
+    pub fn runs_down(&self) -> Vec<Run> {
+        let mut runs: Vec<Run> = Vec::new();
+        for x in 0..self.width {
+            for y in 0..self.depth {
+                if self.is_inside(x, y) && (y == 0 || !self.is_inside(x, y - 1)) {
+                    runs.push(self.run_down(x, y));
+                }
+            }
+        }
+        runs
+    }
+
+    // the distinct lengths of the lines which need to be words, across both axes
+    pub fn line_lengths(&self) -> HashSet<usize> {
+        self.runs_across()
+            .iter()
+            .chain(self.runs_down().iter())
+            .map(|x| x.length)
+            .filter(|x| *x > 1)
+            .collect()
+    }
+}
+
+// Backtracking solver for a shaped grid: it fills each horizontal run in reading order, with
+// a cursor into the index for each vertical run tracking the tiles placed so far
+pub struct ShapeSolver<'a> {
+    shape: &'a Shape,
+    steps: Vec<Run>,
+    step_indices: Vec<Option<&'a BigramIndexTree>>,
+    down_run_at: Vec<Vec<Option<usize>>>,
+    down_indices: Vec<&'a BigramIndexTree>,
+    any_pairchar: HashSet<PairChar>,
+    line_mode: LineMode,
+}
+
+impl<'a> ShapeSolver<'a> {
+    pub fn new(shape: &'a Shape, index_cache: &'a IndexCache) -> Result<ShapeSolver<'a>, String> {
+        let lookup = |length: usize| {
+            index_cache
+                .get(length)
+                .ok_or_else(|| format!("No index for lines of length {}", length))
+        };
+
+        let steps = shape.runs_across();
+        let mut step_indices = Vec::new();
+        for step in &steps {
+            step_indices.push(if step.length > 1 { Some(lookup(step.length)?) } else { None });
+        }
+
+        // only vertical runs of more than one cell constrain the tiles
+        let down_runs: Vec<Run> = shape.runs_down().into_iter().filter(|x| x.length > 1).collect();
+        let mut down_indices = Vec::new();
+        let mut down_run_at = vec![vec![None; shape.depth()]; shape.width()];
+        for (run_number, run) in down_runs.iter().enumerate() {
+            down_indices.push(lookup(run.length)?);
+            for cell in down_run_at[run.x].iter_mut().skip(run.y).take(run.length) {
+                *cell = Some(run_number);
+            }
+        }
+
+        Ok(ShapeSolver {
+            shape,
+            steps,
+            step_indices,
+            down_run_at,
+            down_indices,
+            any_pairchar: PairChar::all_tiles(),
+            line_mode: index_cache.line_mode(),
+        })
+    }
+
+    pub fn new_grid(&self) -> PuzzleGrid {
+        // the grid reads its lines the same way as the indices they were filled from
+        let mut grid = PuzzleGrid::from_shape(self.shape);
+        grid.set_line_mode(self.line_mode);
+        grid
+    }
+
+    // a cursor at the top of each vertical run
+    pub fn new_cursors(&self) -> Vec<IndexCursor<'a>> {
+        self.down_indices.iter().map(|x| x.cursor()).collect()
+    }
+
+    // the possible tiles for each cell of a step, as allowed by the vertical runs
+    fn step_filters(&self, step: &Run, cursors: &[IndexCursor]) -> Vec<HashSet<PairChar>> {
+        (step.x..(step.x + step.length))
+            .map(|x| match self.down_run_at[x][step.y] {
+                Some(run_number) => cursors[run_number].possible_pairchars(),
+                None => self.any_pairchar.clone(),
+            })
+            .collect()
+    }
+
+    // all of the words which could be placed at a step
+    pub fn step_candidates(&self, step_number: usize, cursors: &[IndexCursor]) -> Vec<PairString> {
+        let step = &self.steps[step_number];
+        let filters = self.step_filters(step, cursors);
+
+        match self.step_indices[step_number] {
+            Some(index) => BigramIndexTree::get_candidate_words(index, &filters).collect(),
+            None => filters[0].iter().map(|x| PairString::assemble(&[*x])).collect(),
+        }
+    }
+
+    // place a word at a step, then try to fill out the rest of the grid, undoing the step if
+    // that fails
+    pub fn try_step(
+        &self,
+        grid: &mut PuzzleGrid,
+        cursors: &mut [IndexCursor<'a>],
+        step_number: usize,
+        word: &PairString,
+        continue_running: Option<&AtomicBool>,
+    ) -> bool {
+        let step = self.steps[step_number];
+
+        for (offset, pairchar) in word.slice().iter().enumerate() {
+            grid.set_tile(step.x + offset, step.y, *pairchar);
+            if let Some(run_number) = self.down_run_at[step.x + offset][step.y] {
+                cursors[run_number].descend(*pairchar);
+            }
+        }
+
+        if self.populate_step(grid, cursors, step_number + 1, continue_running) {
+            return true;
+        }
+
+        for offset in 0..step.length {
+            if let Some(run_number) = self.down_run_at[step.x + offset][step.y] {
+                cursors[run_number].ascend();
+            }
+        }
+
+        false
+    }
+
+    // recursion function for populate_shape
+    fn populate_step(
+        &self,
+        grid: &mut PuzzleGrid,
+        cursors: &mut [IndexCursor<'a>],
+        step_number: usize,
+        continue_running: Option<&AtomicBool>,
+    ) -> bool {
+        if continue_running.is_some_and(|x| !x.load(Ordering::Relaxed)) {
+            return false;
+        }
+
+        if step_number == self.steps.len() {
+            // the filters will have kept every vertical run to words in the index
+            return !grid.breaks_repeats();
+        }
+
+        let step = &self.steps[step_number];
+        let filters = self.step_filters(step, cursors);
+
+        match self.step_indices[step_number] {
+            Some(index) => {
+                for word in BigramIndexTree::get_candidate_words(index, &filters) {
+                    if self.try_step(grid, cursors, step_number, &word, continue_running) {
+                        return true;
+                    }
+                }
+            }
+            None => {
+                for pairchar in &filters[0] {
+                    let word = PairString::assemble(&[*pairchar]);
+                    if self.try_step(grid, cursors, step_number, &word, continue_running) {
+                        return true;
+                    }
+                }
+            }
+        }
+
+        false
+    }
+}
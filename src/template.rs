@@ -0,0 +1,87 @@
+use std::collections::HashSet;
+
+// the blank layout of a single line, true marking a blank tile
+pub type BlankPattern = Vec<bool>;
+
+// A crossword-style template, marking which cells of the grid must be blank ("#") and which
+// must hold letters (".")
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Template {
+    rows: Vec<BlankPattern>,
+    width: usize,
+    depth: usize,
+}
+
+impl Template {
+    // read a template with one line per row, whitespace between the cells is optional
+    pub fn parse(template_text: &str) -> Result<Template, String> {
+        let mut rows: Vec<BlankPattern> = Vec::new();
+
+        for (line_number, line) in template_text.lines().enumerate() {
+            let mut row = BlankPattern::new();
+            for cell in line.chars().filter(|x| !x.is_whitespace()) {
+                match cell {
+                    '#' => row.push(true),
+                    '.' => row.push(false),
+                    _ => {
+                        return Err(format!("Invalid template cell '{}' on line {}", cell, line_number + 1));
+                    }
+                }
+            }
+
+            if row.is_empty() {
+                continue;
+            }
+
+            if !rows.is_empty() && row.len() != rows[0].len() {
+                return Err(format!(
+                    "Expected {} cells on line {}, found {}",
+                    rows[0].len(),
+                    line_number + 1,
+                    row.len()
+                ));
+            }
+            rows.push(row);
+        }
+
+        if rows.is_empty() {
+            return Err("No cells found in template".to_string());
+        }
+
+        Ok(Template {
+            width: rows[0].len(),
+            depth: rows.len(),
+            rows,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn is_blank(&self, x: usize, y: usize) -> bool {
+        self.rows[y][x]
+    }
+
+    pub fn row_pattern(&self, y: usize) -> BlankPattern {
+        self.rows[y].clone()
+    }
+
+    pub fn column_pattern(&self, x: usize) -> BlankPattern {
+        self.rows.iter().map(|row| row[x]).collect()
+    }
+
+    // the distinct blank layouts used by the rows, which the horizontal index needs to hold
+    pub fn row_patterns(&self) -> HashSet<BlankPattern> {
+        (0..self.depth).map(|y| self.row_pattern(y)).collect()
+    }
+
+    // the distinct blank layouts used by the columns, which the vertical index needs to hold
+    pub fn column_patterns(&self) -> HashSet<BlankPattern> {
+        (0..self.width).map(|x| self.column_pattern(x)).collect()
+    }
+}
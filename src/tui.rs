@@ -0,0 +1,312 @@
+use super::editor::GridEditor;
+use super::puzzlegrid::Direction;
+use super::types::PairChar;
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use std::fs;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const SUGGESTION_COUNT: usize = 9;
+const AUTOCOMPLETE_TIME_LIMIT: Duration = Duration::from_secs(30);
+const HELP: &str = "arrows move, type two letters per tile, space for a blank, backspace/delete to open a tile, \
+                    tab to turn, 1-9 place a suggestion, ^A fill in the rest, ^K open the line, ^Z undo, ^Y redo, \
+                    ^S save, esc quit";
+
+// raw mode on the alternate screen for as long as the editor runs, put back however it exits
+struct RawScreen;
+
+impl RawScreen {
+    fn enter() -> io::Result<RawScreen> {
+        terminal::enable_raw_mode()?;
+        if let Err(e) = execute!(io::stdout(), EnterAlternateScreen, Hide) {
+            let _ = terminal::disable_raw_mode();
+            return Err(e);
+        }
+        Ok(RawScreen)
+    }
+}
+
+impl Drop for RawScreen {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+// The cursor, the line it's on (its row going across, its column going down), the first
+// letter of a tile being typed, and the file name being typed when saving
+struct EditorState {
+    x: usize,
+    y: usize,
+    direction: Direction,
+    pending: Option<char>,
+    save_prompt: Option<String>,
+    save_file: String,
+    message: String,
+}
+
+impl EditorState {
+    fn line_index(&self) -> usize {
+        match self.direction {
+            Direction::Across => self.y,
+            Direction::Down => self.x,
+        }
+    }
+
+    fn in_line(&self, x: usize, y: usize) -> bool {
+        match self.direction {
+            Direction::Across => y == self.y,
+            Direction::Down => x == self.x,
+        }
+    }
+
+    fn move_by(&mut self, editor: &GridEditor, dx: isize, dy: isize) {
+        self.pending = None;
+        self.x = (self.x as isize + dx).clamp(0, editor.width() as isize - 1) as usize;
+        self.y = (self.y as isize + dy).clamp(0, editor.depth() as isize - 1) as usize;
+    }
+
+    // one tile along the line, forwards or back, stopping at its ends
+    fn step(&mut self, editor: &GridEditor, forwards: bool) {
+        let distance = if forwards { 1 } else { -1 };
+        match self.direction {
+            Direction::Across => self.move_by(editor, distance, 0),
+            Direction::Down => self.move_by(editor, 0, distance),
+        }
+    }
+
+    fn set_tile(&mut self, editor: &mut GridEditor, tile: &str) -> Result<(), String> {
+        editor.set_tile(self.x, self.y, PairChar::parse(tile)?)
+    }
+
+    // act on a key, false once the editor should close
+    fn handle_key(&mut self, editor: &mut GridEditor, key: KeyEvent) -> bool {
+        if self.save_prompt.is_some() {
+            self.handle_save_key(editor, key);
+            return true;
+        }
+
+        self.message.clear();
+        let movement = match key.code {
+            KeyCode::Left => Some((-1, 0)),
+            KeyCode::Right => Some((1, 0)),
+            KeyCode::Up => Some((0, -1)),
+            KeyCode::Down => Some((0, 1)),
+            _ => None,
+        };
+        if let Some((dx, dy)) = movement {
+            self.move_by(editor, dx, dy);
+            return true;
+        }
+
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        let result = match key.code {
+            KeyCode::Esc => return false,
+            KeyCode::Char('c') | KeyCode::Char('q') if control => return false,
+            KeyCode::Char('a') if control => {
+                self.autocomplete(editor);
+                Ok(())
+            }
+            KeyCode::Char('k') if control => editor.clear_line(self.direction, self.line_index()),
+            KeyCode::Char('z') if control => {
+                if !editor.undo() {
+                    self.message = "Nothing to undo".to_string();
+                }
+                Ok(())
+            }
+            KeyCode::Char('y') if control => {
+                if !editor.redo() {
+                    self.message = "Nothing to redo".to_string();
+                }
+                Ok(())
+            }
+            KeyCode::Char('s') if control => {
+                self.save_prompt = Some(self.save_file.clone());
+                Ok(())
+            }
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.pending = None;
+                self.direction = match self.direction {
+                    Direction::Across => Direction::Down,
+                    Direction::Down => Direction::Across,
+                };
+                Ok(())
+            }
+            KeyCode::Char(c) if c.is_ascii_digit() && c != '0' => {
+                let number = c.to_digit(10).unwrap() as usize;
+                match editor.suggestions(self.direction, self.line_index(), SUGGESTION_COUNT).get(number - 1) {
+                    Some(word) => editor.place_word(self.direction, self.line_index(), &word.decode()),
+                    None => Err(format!("There is no suggestion {}", number)),
+                }
+            }
+            KeyCode::Char(c) if c.is_ascii_alphabetic() => match self.pending.take() {
+                None => {
+                    self.pending = Some(c.to_ascii_lowercase());
+                    Ok(())
+                }
+                Some(first) => {
+                    let tile = format!("{}{}", first, c.to_ascii_lowercase());
+                    self.set_tile(editor, &tile).map(|_| self.step(editor, true))
+                }
+            },
+            KeyCode::Char(' ') | KeyCode::Char('_') => self.set_tile(editor, "__").map(|_| self.step(editor, true)),
+            KeyCode::Backspace if self.pending.is_some() => {
+                self.pending = None;
+                Ok(())
+            }
+            // open the tile just typed, which is the one behind the cursor unless it stopped at
+            // the end of the line
+            KeyCode::Backspace => {
+                if editor.get_tile(self.x, self.y).is_wildcard() {
+                    self.step(editor, false);
+                }
+                self.set_tile(editor, "??")
+            }
+            KeyCode::Delete => self.set_tile(editor, "??"),
+            _ => Ok(()),
+        };
+
+        if let Err(e) = result {
+            self.message = e;
+        }
+        true
+    }
+
+    // run the solver over the open tiles, while another thread watches for escape (raw mode
+    // swallows ctrl-c) or the time limit running out and stops it
+    fn autocomplete(&mut self, editor: &mut GridEditor) {
+        self.message = "Filling in the rest of the grid, esc to stop".to_string();
+        let _ = draw(&mut io::stdout(), editor, self);
+
+        let continue_running = AtomicBool::new(true);
+        let finished = AtomicBool::new(false);
+        let completed = thread::scope(|scope| {
+            scope.spawn(|| {
+                let deadline = Instant::now() + AUTOCOMPLETE_TIME_LIMIT;
+                while !finished.load(Ordering::Relaxed) {
+                    if Instant::now() >= deadline {
+                        continue_running.store(false, Ordering::Relaxed);
+                        return;
+                    }
+                    // other keys pressed while the solver runs are dropped
+                    if let Ok(true) = event::poll(Duration::from_millis(100)) {
+                        if let Ok(Event::Key(KeyEvent { code: KeyCode::Esc, .. })) = event::read() {
+                            continue_running.store(false, Ordering::Relaxed);
+                            return;
+                        }
+                    }
+                }
+            });
+
+            let completed = editor.autocomplete_until(&continue_running);
+            finished.store(true, Ordering::Relaxed);
+            completed
+        });
+
+        self.message = if completed {
+            String::new()
+        } else if !continue_running.load(Ordering::Relaxed) {
+            "Stopped filling in the grid".to_string()
+        } else {
+            "No way to fill in the rest of the grid".to_string()
+        };
+    }
+
+    // type the name of the file to save to, in the text format read by --seed
+    fn handle_save_key(&mut self, editor: &GridEditor, key: KeyEvent) {
+        let file = self.save_prompt.as_mut().unwrap();
+        match key.code {
+            KeyCode::Esc => self.save_prompt = None,
+            KeyCode::Backspace => {
+                file.pop();
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => file.push(c),
+            KeyCode::Enter if !file.is_empty() => {
+                let file = self.save_prompt.take().unwrap();
+                self.message = match fs::write(&file, editor.grid().to_text()) {
+                    Ok(()) => format!("Saved {}", file),
+                    Err(e) => format!("Failed to write {}: {}", file, e),
+                };
+                self.save_file = file;
+            }
+            _ => (),
+        }
+    }
+}
+
+// edit a grid in the terminal until escape is pressed: the cursor's tile is shown reversed and
+// the rest of its line underlined, tiles which no word fits are drawn in red, and the words
+// which fit the cursor's line are listed underneath, numbered to place them
+pub fn run_editor(editor: &mut GridEditor) -> io::Result<()> {
+    let _screen = RawScreen::enter()?;
+    let mut out = io::stdout();
+    let mut state = EditorState {
+        x: 0,
+        y: 0,
+        direction: Direction::Across,
+        pending: None,
+        save_prompt: None,
+        save_file: String::new(),
+        message: String::new(),
+    };
+
+    loop {
+        draw(&mut out, editor, &state)?;
+        match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press && !state.handle_key(editor, key) => return Ok(()),
+            // anything else, including a resize, just redraws
+            _ => (),
+        }
+    }
+}
+
+fn draw(out: &mut impl Write, editor: &GridEditor, state: &EditorState) -> io::Result<()> {
+    let dead_cells = editor.dead_cells();
+
+    queue!(out, Clear(ClearType::All))?;
+    for y in 0..editor.depth() {
+        queue!(out, MoveTo(0, y as u16))?;
+        for x in 0..editor.width() {
+            let mut tile = editor.get_tile(x, y).decode();
+            if (x, y) == (state.x, state.y) {
+                if let Some(c) = state.pending {
+                    tile = format!("{}?", c);
+                }
+                queue!(out, SetAttribute(Attribute::Reverse))?;
+            } else if state.in_line(x, y) {
+                queue!(out, SetAttribute(Attribute::Underlined))?;
+            }
+            if dead_cells.contains(&(x, y)) {
+                queue!(out, SetForegroundColor(Color::Red))?;
+            }
+            queue!(out, Print(tile), SetAttribute(Attribute::Reset), ResetColor, Print(" "))?;
+        }
+    }
+
+    let mut row = editor.depth() as u16 + 1;
+    let suggestions = editor.suggestions(state.direction, state.line_index(), SUGGESTION_COUNT);
+    queue!(out, MoveTo(0, row), Print(format!("{} {}: ", state.direction, state.line_index() + 1)))?;
+    if suggestions.is_empty() {
+        queue!(out, SetForegroundColor(Color::Red), Print("no words fit"), ResetColor)?;
+    }
+    for (i, word) in suggestions.iter().enumerate() {
+        row += 1;
+        queue!(out, MoveTo(2, row), Print(format!("{} {}", i + 1, word.decode())))?;
+    }
+
+    row += 2;
+    queue!(out, MoveTo(0, row))?;
+    match &state.save_prompt {
+        Some(file) => queue!(out, Print(format!("Save to: {}", file)))?,
+        None => queue!(out, Print(&state.message))?,
+    }
+    queue!(out, MoveTo(0, row + 1), Print(HELP))?;
+
+    out.flush()
+}
@@ -2,16 +2,18 @@ use std::collections::HashSet;
 use std::fmt;
 use std::ops::Index;
 
-#[derive(Clone, Copy, Default, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Default, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct PairChar {
     pair_char: u16,
 }
 
 const BLANK_VALUE: u16 = 27 * 27;
+const WILDCARD_VALUE: u16 = BLANK_VALUE + 1;
+const OUTSIDE_VALUE: u16 = BLANK_VALUE + 2;
 
 impl PairChar {
     pub fn new() -> PairChar {
-        let pair_char = std::u16::MAX;
+        let pair_char = u16::MAX;
 
         PairChar { pair_char }
     }
@@ -20,8 +22,33 @@ impl PairChar {
         self.pair_char == BLANK_VALUE
     }
 
+    // wildcards stand in for any pairchar (including a blank) in a partially filled grid
+    pub fn is_wildcard(&self) -> bool {
+        self.pair_char == WILDCARD_VALUE
+    }
+
+    // marks a cell of a shaped grid's bounding box which isn't part of the puzzle
+    pub fn outside() -> PairChar {
+        PairChar {
+            pair_char: OUTSIDE_VALUE,
+        }
+    }
+
+    pub fn is_outside(&self) -> bool {
+        self.pair_char == OUTSIDE_VALUE
+    }
+
+    // every tile a line could hold: each pair of letters, and the blank
+    pub fn all_tiles() -> HashSet<PairChar> {
+        let mut tiles: HashSet<PairChar> = (b'a'..=b'z')
+            .flat_map(|x| (b'a'..=b'z').map(move |y| PairChar::encode(x, y)))
+            .collect();
+        tiles.insert(PairChar::encode(b'_', b'_'));
+        tiles
+    }
+
     fn is_lowercase_ascii(test_char: u8) -> bool {
-        test_char >= b'a' && test_char <= b'z'
+        test_char.is_ascii_lowercase()
     }
 
     fn single_char_convert(test_char: u8) -> u16 {
@@ -36,6 +63,10 @@ impl PairChar {
     pub fn encode(char1: u8, char2: u8) -> PairChar {
         if char1 == b'_' && char2 == b'_' {
             PairChar { pair_char: BLANK_VALUE }
+        } else if char1 == b'?' && char2 == b'?' {
+            PairChar { pair_char: WILDCARD_VALUE }
+        } else if char1 == b'#' && char2 == b'#' {
+            PairChar::outside()
         } else {
             let val1: u16 = PairChar::single_char_convert(char1);
             let val2: u16 = PairChar::single_char_convert(char2);
@@ -46,7 +77,31 @@ impl PairChar {
         }
     }
 
+    // read a single tile as written by PuzzleGrid::print, checking that it can be encoded
+    pub fn parse(tile: &str) -> Result<PairChar, String> {
+        let bytes = tile.as_bytes();
+        let is_valid = bytes.len() == 2
+            && (tile == "__"
+                || tile == "??"
+                || tile == "##"
+                || bytes.iter().all(|x| PairChar::is_lowercase_ascii(*x)));
+
+        if is_valid {
+            Ok(PairChar::encode(bytes[0], bytes[1]))
+        } else {
+            Err(format!("Invalid tile: {}", tile))
+        }
+    }
+
     pub fn decode(&self) -> String {
+        if self.is_wildcard() {
+            return "??".to_string();
+        }
+
+        if self.is_outside() {
+            return "##".to_string();
+        }
+
         if self.pair_char >= (27 * 26) {
             return "__".to_string();
         }
@@ -151,6 +206,14 @@ impl PairString {
     pub fn reverse(&mut self) {
         self.pair_string.reverse();
     }
+
+    // shift every pairchar to the right, wrapping those at the end around to the start
+    pub fn rotate_right(&mut self, count: usize) {
+        if !self.is_empty() {
+            let count = count % self.len();
+            self.pair_string.rotate_right(count);
+        }
+    }
 }
 
 impl fmt::Display for PairString {
@@ -169,33 +232,3 @@ impl Index<usize> for PairString {
 }
 
 pub type WordList = Vec<PairString>;
-
-// Store possible words in a WordIterator
-pub struct WordIterator {
-    candidates: WordList,
-    next: usize,
-}
-
-impl WordIterator {
-    pub fn new(list: WordList) -> WordIterator {
-        WordIterator {
-            candidates: list,
-            next: 0,
-        }
-    }
-}
-
-impl Iterator for WordIterator {
-    type Item = PairString;
-
-    fn next(&mut self) -> Option<PairString> {
-        if self.next >= self.candidates.len() {
-            None
-        } else {
-            let return_val = self.candidates[self.next].clone();
-            self.next += 1;
-            Some(return_val)
-        }
-    }
-}
-
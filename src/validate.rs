@@ -0,0 +1,127 @@
+use super::puzzlegrid::{Direction, PuzzleGrid};
+use super::types::{PairChar, PairString};
+use super::wordstore::{LineMode, WordStore, Wrap};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+// Why a line of a grid isn't allowed
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LineError {
+    Incomplete,
+    NotAWord,
+    Duplicate(Direction, usize),
+}
+
+// A line of a grid which breaks the rules, by direction and row or column number (from 0)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InvalidLine {
+    pub direction: Direction,
+    pub index: usize,
+    pub word: String,
+    pub error: LineError,
+}
+
+impl fmt::Display for InvalidLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let line_name = |direction: Direction| match direction {
+            Direction::Across => "row",
+            Direction::Down => "column",
+        };
+
+        write!(f, "{} {} '{}' ", line_name(self.direction), self.index + 1, self.word)?;
+        match &self.error {
+            LineError::Incomplete => write!(f, "has tiles still to fill in"),
+            LineError::NotAWord => write!(f, "isn't a word"),
+            LineError::Duplicate(direction, index) => write!(f, "repeats {} {}", line_name(*direction), index + 1),
+        }
+    }
+}
+
+// check every row and column of a grid against the word store, in the same way as the solver
+// builds its lines (the word with up to max_blanks blanks around it, split on blanks for
+// separated lines, and rotated on a toroidal grid), and look for repeats
+// lines crossing cells outside a shape are checked a segment at a time, as in the shape solver
+pub fn validate_grid(
+    grid: &PuzzleGrid,
+    word_store: &WordStore,
+    max_blanks: usize,
+    line_mode: LineMode,
+    wrap: Wrap,
+) -> Vec<InvalidLine> {
+    let mut lines: Vec<(Direction, usize, Vec<PairChar>)> = Vec::new();
+    for y in 0..grid.depth() {
+        lines.push((Direction::Across, y, (0..grid.width()).map(|x| grid.get_tile(x, y)).collect()));
+    }
+    for x in 0..grid.width() {
+        lines.push((Direction::Down, x, (0..grid.depth()).map(|y| grid.get_tile(x, y)).collect()));
+    }
+
+    let mut rules_grid = grid.clone();
+    rules_grid.set_line_mode(line_mode);
+    rules_grid.set_wrap(wrap);
+    let check_duplicates = rules_grid.has_duplicates();
+
+    let mut invalid_lines: Vec<InvalidLine> = Vec::new();
+    let mut first_seen: HashMap<Vec<PairChar>, (Direction, usize)> = HashMap::new();
+    let mut legal_lines: HashMap<usize, HashSet<PairString>> = HashMap::new();
+
+    for (direction, index, line) in lines {
+        for segment in line.split(PairChar::is_outside).filter(|x| x.len() > 1) {
+            let words: Vec<Vec<PairChar>> = match line_mode {
+                LineMode::Joined => vec![segment.iter().filter(|x| !x.is_blank()).copied().collect()],
+                LineMode::Separated(_) => segment
+                    .split(|x| x.is_blank())
+                    .filter(|x| x.len() > 1)
+                    .map(|x| x.to_vec())
+                    .collect(),
+            };
+            let invalid_line = |error: LineError| InvalidLine {
+                direction,
+                index,
+                word: words.iter().map(|x| PairString::assemble(x).decode()).collect::<Vec<String>>().join(" "),
+                error,
+            };
+
+            if segment.iter().any(PairChar::is_wildcard) {
+                invalid_lines.push(invalid_line(LineError::Incomplete));
+                continue;
+            }
+
+            let fits_line = match (line_mode, wrap) {
+                // joined flat lines are quick to check directly, rather than listing every line
+                (LineMode::Joined, Wrap::Flat) => {
+                    let pair_word = PairString::assemble(&words[0]);
+                    word_store.contains(&pair_word)
+                        && WordStore::permute_to_fill(&pair_word, segment.len(), max_blanks)
+                            .iter()
+                            .any(|x| x.slice() == segment)
+                }
+                _ => legal_lines
+                    .entry(segment.len())
+                    .or_insert_with(|| {
+                        let lines = word_store.lines_by_length(segment.len(), max_blanks, line_mode);
+                        WordStore::wrap_lines(lines, wrap).into_iter().collect()
+                    })
+                    .contains(&PairString::assemble(segment)),
+            };
+            if !fits_line {
+                invalid_lines.push(invalid_line(LineError::NotAWord));
+            }
+
+            if check_duplicates {
+                for word in &words {
+                    match first_seen.get(&rules_grid.wrap_word(word.clone())) {
+                        Some((first_direction, first_index)) => {
+                            invalid_lines.push(invalid_line(LineError::Duplicate(*first_direction, *first_index)));
+                        }
+                        None => {
+                            first_seen.insert(rules_grid.wrap_word(word.clone()), (direction, index));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    invalid_lines
+}
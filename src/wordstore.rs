@@ -1,9 +1,75 @@
-use super::types::{PairString, WordList};
+use super::template::BlankPattern;
+use super::types::{PairChar, PairString, WordList};
+use std::collections::HashSet;
+use std::str::FromStr;
+
+// How the dictionary words are laid out along a line of the grid
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum LineMode {
+    // blanks are skipped over, so the rest of the line reads as a single word
+    #[default]
+    Joined,
+    // each run of blanks separates the line into independent words, as in a crossword
+    Separated(SingleTiles),
+}
+
+// Whether the lines of the grid wrap around, as on the surface of a torus
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Wrap {
+    #[default]
+    Flat,
+    // the line is valid if any rotation of it is valid
+    AnyRotation,
+    // the line is valid if it's valid when read from this tile, wrapping around the end
+    Rotation(usize),
+}
+
+impl FromStr for Wrap {
+    type Err = String;
+
+    fn from_str(wrap: &str) -> Result<Wrap, String> {
+        match wrap {
+            "none" => Ok(Wrap::Flat),
+            "any" => Ok(Wrap::AnyRotation),
+            _ => wrap
+                .parse::<usize>()
+                .map(Wrap::Rotation)
+                .map_err(|_| format!("Unknown rotation: {}", wrap)),
+        }
+    }
+}
+
+// What a separated line may hold in a single tile between blanks (or the edge of the grid)
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SingleTiles {
+    Forbid,
+    Dictionary,
+    Unchecked,
+}
+
+impl FromStr for SingleTiles {
+    type Err = String;
+
+    fn from_str(single_tiles: &str) -> Result<SingleTiles, String> {
+        match single_tiles {
+            "forbid" => Ok(SingleTiles::Forbid),
+            "dictionary" => Ok(SingleTiles::Dictionary),
+            "unchecked" => Ok(SingleTiles::Unchecked),
+            _ => Err(format!("Unknown single tile rule: {}", single_tiles)),
+        }
+    }
+}
 
 pub struct WordStore {
     word_store: Vec<WordList>,
 }
 
+impl Default for WordStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl WordStore {
     pub fn new() -> WordStore {
         let size = 12;
@@ -28,7 +94,7 @@ impl WordStore {
 
         // grow the word_store if required
         if index_pt >= self.word_store.len() {
-            println!("Growing the wordstore");
+            eprintln!("Growing the wordstore");
             self.word_store.resize_with(index_pt + 1, Vec::new);
         }
 
@@ -36,6 +102,29 @@ impl WordStore {
         self.word_store[index_pt].push(PairString::encode(word));
     }
 
+    // remove every copy of a word from the word_store, returning whether it was present
+    pub fn remove(&mut self, word: &str) -> bool {
+        if word.is_empty() || word.len() % 2 == 1 || word.len() / 2 > self.word_store.len() {
+            return false;
+        }
+
+        let pair_word = PairString::encode(word);
+        let word_list = &mut self.word_store[(word.len() / 2) - 1];
+        let original_len = word_list.len();
+        word_list.retain(|x| *x != pair_word);
+
+        word_list.len() != original_len
+    }
+
+    pub fn contains(&self, word: &PairString) -> bool {
+        !word.is_empty() && word.len() <= self.word_store.len() && self.word_store[word.len() - 1].contains(word)
+    }
+
+    // the longest line, in tiles, that the store keeps words for
+    pub fn max_length(&self) -> usize {
+        self.word_store.len()
+    }
+
     pub fn words_by_length(&self, pattern_size: usize) -> &WordList {
         &self.word_store[pattern_size - 1]
     }
@@ -47,6 +136,197 @@ impl WordStore {
         }
 
         let mut return_list = WordList::new();
+        for (word_size, blanks) in WordStore::blank_padded_sizes(pattern_size, max_blanks) {
+            for word in self.word_store[word_size - 1].iter() {
+                for permutation in word.permute(blanks) {
+                    return_list.push(permutation);
+                }
+            }
+        }
+        return_list
+    }
+
+    // place each word into every blank layout of the given size, rather than generating all of
+    // the blank permutations
+    pub fn patterned_words_by_length(
+        &self,
+        pattern_size: usize,
+        patterns: &HashSet<BlankPattern>,
+        line_mode: LineMode,
+    ) -> WordList {
+        let blank = PairChar::encode(b'_', b'_');
+        let mut return_list = WordList::new();
+
+        if let LineMode::Separated(single_tiles) = line_mode {
+            let single_tile_words = self.single_tile_words(single_tiles);
+            for pattern in patterns.iter().filter(|x| x.len() == pattern_size) {
+                if pattern.iter().any(|is_blank| !is_blank) {
+                    self.fill_pattern(pattern, &single_tile_words, &mut PairString::new(), &mut return_list);
+                }
+            }
+            return return_list;
+        }
+
+        for pattern in patterns.iter().filter(|x| x.len() == pattern_size) {
+            let word_size = pattern.iter().filter(|x| !**x).count();
+            if word_size == 0 || word_size > self.word_store.len() {
+                continue;
+            }
+
+            for word in &self.word_store[word_size - 1] {
+                let mut letters = word.slice().iter();
+                let pair_string = pattern
+                    .iter()
+                    .map(|is_blank| if *is_blank { blank } else { *letters.next().unwrap() })
+                    .collect();
+
+                return_list.push(PairString { pair_string });
+            }
+        }
+        return_list
+    }
+
+    // every line of the given size which can be built from the dictionary in this line mode
+    pub fn lines_by_length(&self, pattern_size: usize, max_blanks: usize, line_mode: LineMode) -> WordList {
+        if line_mode == LineMode::Joined {
+            return self.permuted_words_by_length(pattern_size, max_blanks);
+        }
+
+        // separated lines use the same numbers of blanks as joined ones, but the blanks can go
+        // anywhere so long as each run of tiles between them is a word
+        let mut patterns: HashSet<BlankPattern> = HashSet::new();
+        for (_, blanks) in WordStore::blank_padded_sizes(pattern_size, max_blanks) {
+            WordStore::blank_layouts(pattern_size, blanks, &mut BlankPattern::new(), &mut patterns);
+        }
+
+        self.patterned_words_by_length(pattern_size, &patterns, line_mode)
+    }
+
+    // rotate a set of lines for a toroidal grid, for any rotation this adds every rotation of
+    // every line (the index will merge any which are repeated)
+    pub fn wrap_lines(lines: WordList, wrap: Wrap) -> WordList {
+        match wrap {
+            Wrap::Flat => lines,
+            Wrap::Rotation(offset) => lines
+                .into_iter()
+                .map(|mut x| {
+                    x.rotate_right(offset);
+                    x
+                })
+                .collect(),
+            Wrap::AnyRotation => {
+                let mut return_list = WordList::new();
+                for line in lines {
+                    for offset in 0..line.len() {
+                        let mut rotated_line = line.clone();
+                        rotated_line.rotate_right(offset);
+                        return_list.push(rotated_line);
+                    }
+                }
+                return_list
+            }
+        }
+    }
+
+    // recursively generate all the layouts of a number of blanks in a line
+    fn blank_layouts(
+        pattern_size: usize,
+        blanks: usize,
+        pattern: &mut BlankPattern,
+        patterns: &mut HashSet<BlankPattern>,
+    ) {
+        if pattern.len() == pattern_size {
+            patterns.insert(pattern.clone());
+            return;
+        }
+
+        let placed_blanks = pattern.iter().filter(|x| **x).count();
+        let remaining_tiles = pattern_size - pattern.len();
+
+        if placed_blanks < blanks {
+            pattern.push(true);
+            WordStore::blank_layouts(pattern_size, blanks, pattern, patterns);
+            pattern.pop();
+        }
+
+        // only place a letter if there's still room for the rest of the blanks
+        if blanks - placed_blanks < remaining_tiles {
+            pattern.push(false);
+            WordStore::blank_layouts(pattern_size, blanks, pattern, patterns);
+            pattern.pop();
+        }
+    }
+
+    // recursively fill a blank layout with words, one for each run of tiles between the blanks
+    fn fill_pattern(
+        &self,
+        pattern: &[bool],
+        single_tile_words: &[PairString],
+        line: &mut PairString,
+        lines: &mut WordList,
+    ) {
+        let position = line.len();
+        if position == pattern.len() {
+            lines.push(line.clone());
+            return;
+        }
+
+        if pattern[position] {
+            line.push(PairChar::encode(b'_', b'_'));
+            self.fill_pattern(pattern, single_tile_words, line, lines);
+            line.pair_string.pop();
+            return;
+        }
+
+        let run_length = pattern[position..].iter().take_while(|x| !**x).count();
+        let words: &[PairString] = if run_length == 1 {
+            single_tile_words
+        } else if run_length <= self.word_store.len() {
+            &self.word_store[run_length - 1]
+        } else {
+            return;
+        };
+
+        for word in words {
+            line.pair_string.extend_from_slice(word.slice());
+            self.fill_pattern(pattern, single_tile_words, line, lines);
+            line.pair_string.truncate(position);
+        }
+    }
+
+    // the words which may fill a single tile between blanks in a separated line
+    fn single_tile_words(&self, single_tiles: SingleTiles) -> WordList {
+        match single_tiles {
+            SingleTiles::Forbid => WordList::new(),
+            SingleTiles::Dictionary => self.word_store[0].clone(),
+            SingleTiles::Unchecked => {
+                let pairchars: HashSet<PairChar> =
+                    self.word_store.iter().flatten().flat_map(|x| x.slice().to_vec()).collect();
+                pairchars.into_iter().map(|x| PairString::assemble(&[x])).collect()
+            }
+        }
+    }
+
+    // the blank permutations of a single word which fill a pattern of the given size, matching
+    // those generated for it by permuted_words_by_length
+    pub fn permute_to_fill(word: &PairString, pattern_size: usize, max_blanks: usize) -> WordList {
+        match WordStore::blank_padded_sizes(pattern_size, max_blanks)
+            .into_iter()
+            .find(|(word_size, _)| *word_size == word.len())
+        {
+            Some((_, 0)) => vec![word.clone()],
+            Some((_, blanks)) => word.permute(blanks),
+            None => WordList::new(),
+        }
+    }
+
+    // the (word size, blank count) pairs used to fill a pattern of the given size, with words
+    // padded by at least one blank but never more than half the pattern
+    pub fn blank_padded_sizes(pattern_size: usize, max_blanks: usize) -> Vec<(usize, usize)> {
+        if max_blanks == 0 {
+            return vec![(pattern_size, 0)];
+        }
+
         let mut min_bound = if pattern_size % 2 == 0 {
             pattern_size / 2
         } else {
@@ -57,15 +337,26 @@ impl WordStore {
             min_bound = pattern_size - max_blanks;
         }
 
-        for word_size in min_bound..pattern_size {
-            let blanks = pattern_size - word_size;
-            for word in self.word_store[word_size - 1].iter().cloned() {
-                for permutation in word.permute(blanks) {
-                    return_list.push(permutation);
-                }
+        (min_bound..pattern_size)
+            .map(|word_size| (word_size, pattern_size - word_size))
+            .collect()
+    }
+
+    // a short hash identifying the set of words in the store, whatever order they were added
+    // in (64 bit FNV-1a over the sorted words)
+    pub fn fingerprint(&self) -> String {
+        let mut words: Vec<String> = self.word_store.iter().flatten().map(PairString::decode).collect();
+        words.sort();
+
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for word in &words {
+            for byte in word.bytes().chain(std::iter::once(b'\n')) {
+                hash ^= u64::from(byte);
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
             }
         }
-        return_list
+
+        format!("{:016x}", hash)
     }
 
     pub fn print(&self) {
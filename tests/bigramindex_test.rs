@@ -0,0 +1,98 @@
+use puzzler::bigramindex::BigramIndexTree;
+use puzzler::types::{PairChar, PairString};
+use std::collections::HashSet;
+
+fn pairchar_set(pairs: &[&str]) -> HashSet<PairChar> {
+    pairs
+        .iter()
+        .map(|x| PairChar::encode(x.as_bytes()[0], x.as_bytes()[1]))
+        .collect()
+}
+
+#[test]
+fn candidate_words_match_filters() {
+    let word_store = puzzler::generate_wordstore("tests/words-good-4x4");
+    let index = BigramIndexTree::build(4, &word_store, 0);
+
+    let any_pairchar = index.get_keys_as_hashset();
+    let filters = vec![pairchar_set(&["di", "st"]), pairchar_set(&["st", "me"]), any_pairchar.clone(), any_pairchar];
+
+    let candidates: HashSet<PairString> = BigramIndexTree::get_candidate_words(&index, &filters).collect();
+    let expected: HashSet<PairString> = ["distrust", "dimeride"].iter().map(|x| PairString::encode(x)).collect();
+
+    assert_eq!(candidates, expected);
+}
+
+#[test]
+fn candidate_words_empty_filter() {
+    let word_store = puzzler::generate_wordstore("tests/words-good-4x4");
+    let index = BigramIndexTree::build(4, &word_store, 0);
+
+    let any_pairchar = index.get_keys_as_hashset();
+    let filters = vec![any_pairchar.clone(), HashSet::new(), any_pairchar.clone(), any_pairchar];
+
+    assert_eq!(BigramIndexTree::get_candidate_words(&index, &filters).count(), 0);
+}
+
+#[test]
+fn cursor_matches_stem_lookup() {
+    let word_store = puzzler::generate_wordstore("tests/words-good-4x4");
+    let index = BigramIndexTree::build(4, &word_store, 0);
+
+    let stem = PairString::encode("dist");
+    let mut cursor = index.cursor();
+    for pairchar in stem.slice() {
+        assert!(cursor.descend(*pairchar));
+    }
+
+    assert_eq!(cursor.depth(), 2);
+    assert_eq!(cursor.possible_pairchars(), pairchar_set(&["ru"]));
+    assert_eq!(vec![cursor.possible_pairchars()], index.get_possible_pairchars(vec![stem.slice()]));
+
+    // stepping off the tree leaves nothing to follow until we step back again
+    assert!(!cursor.descend(PairChar::encode(b'z', b'z')));
+    assert!(cursor.possible_pairchars().is_empty());
+    cursor.ascend();
+    assert_eq!(cursor.possible_pairchars(), pairchar_set(&["ru"]));
+}
+
+#[test]
+fn estimate_matches_built_index() {
+    let word_store = puzzler::generate_wordstore("tests/words-good-spaces-4x4");
+
+    for max_blanks in 0..=2 {
+        let estimate = BigramIndexTree::estimate(4, &word_store, max_blanks);
+        let index = BigramIndexTree::build(4, &word_store, max_blanks);
+
+        assert_eq!(estimate.permuted_words, word_store.permuted_words_by_length(4, max_blanks).len());
+        assert_eq!(estimate.nodes, index.node_count());
+        assert_eq!(estimate.entries, index.entry_count());
+    }
+}
+
+#[test]
+fn insert_and_remove_words() {
+    let mut word_store = puzzler::generate_wordstore("tests/words-good-spaces-4x4");
+    let full_index = BigramIndexTree::build(4, &word_store, 2);
+    let mut index = BigramIndexTree::build(4, &word_store, 2);
+
+    assert!(puzzler::remove_word("zeta", &mut word_store, &mut [(4, &mut index)], 2));
+    assert!(!index.contains(PairString::encode("__ze__ta").slice()));
+    assert_eq!(index, BigramIndexTree::build(4, &word_store, 2));
+
+    assert!(puzzler::insert_word("zeta", &mut word_store, &mut [(4, &mut index)], 2));
+    assert!(index.contains(PairString::encode("__ze__ta").slice()));
+    assert_eq!(index, full_index);
+}
+
+#[test]
+fn remove_prunes_empty_branches() {
+    let mut word_store = puzzler::generate_wordstore("tests/words-good-4x4");
+    let mut index = BigramIndexTree::build(4, &word_store, 0);
+
+    for word in word_store.words_by_length(4).clone() {
+        assert!(index.remove_word(&word, 4, 0));
+    }
+    assert_eq!(index.node_count(), 1);
+    assert!(!word_store.remove("xx"));
+}
@@ -0,0 +1,74 @@
+use puzzler::bigramindex::BigramIndexTree;
+use puzzler::clues;
+use puzzler::puzzlegrid::{Direction, PuzzleGrid};
+use puzzler::report::RunReport;
+use puzzler::{ipuz, puz, render};
+use std::collections::HashMap;
+
+fn read_test_clues() -> HashMap<String, String> {
+    clues::read_clues(&std::fs::read_to_string("tests/clues-3x3").unwrap()).unwrap()
+}
+
+#[test]
+fn missing_clues() {
+    let grid = PuzzleGrid::parse("ab cd __\nef gh ij\n__ kl mn\n").unwrap();
+    let clues = read_test_clues();
+    assert_eq!(clues["efghij"], "Middle row");
+
+    let missing: Vec<(usize, Direction)> = clues::missing_clues(&grid, &clues)
+        .iter()
+        .map(|x| (x.number, x.direction))
+        .collect();
+    assert_eq!(missing, vec![(1, Direction::Down), (2, Direction::Down), (5, Direction::Across)]);
+
+    assert!(clues::read_clues("abcd\n").is_err());
+}
+
+#[test]
+fn clues_in_outputs() {
+    let grid = PuzzleGrid::parse("ab cd __\nef gh ij\n__ kl mn\n").unwrap();
+
+    let mut report = RunReport::new(3, 3, 1);
+    report.set_clues(read_test_clues());
+    let json = report.to_json(Some(&grid));
+    assert_eq!(json["clues"][0]["clue"], "Start of the alphabet");
+    assert_eq!(json["clues"][1]["clue"], serde_json::Value::Null);
+    assert_eq!(json["clues"][4]["direction"], "down");
+
+    let html = render::render_html(&grid, "Proof", Some(&read_test_clues()));
+    assert!(html.contains("<li value=\"3\">Middle row</li>"));
+    assert!(html.contains("<li value=\"2\"></li>"));
+}
+
+#[test]
+fn clues_for_a_solved_grid() {
+    // a joined solve with a blank inside the top row, which ends its first entry
+    let word_store = puzzler::generate_wordstore("tests/words-good-spaces-4x4");
+    let index = BigramIndexTree::build(4, &word_store, 2);
+    let seed_grid = PuzzleGrid::parse("ap er __ ea\n?? ?? ?? ??\n?? ?? ?? ??\n?? ?? ?? ??\n").unwrap();
+    let top_start_words = puzzler::generate_seeded_top_words(&seed_grid, &index, &index);
+    let grid = puzzler::populate_seeded_grid(&seed_grid, &top_start_words, &index, &index).unwrap();
+
+    let entries = grid.entries();
+    let clues: HashMap<String, String> = entries
+        .iter()
+        .map(|x| (x.word.decode(), format!("Clue for {}", x.word.decode())))
+        .collect();
+    assert!(clues::missing_clues(&grid, &clues).is_empty());
+
+    let mut report = RunReport::new(4, 4, 2);
+    report.set_clues(clues.clone());
+    let json = report.to_json(Some(&grid));
+    assert_eq!(json["clues"][0]["clue"], "Clue for aper");
+    assert_eq!(json["clues"][0]["answer"], entries[0].word.decode());
+
+    let ipuz = ipuz::write_ipuz(&grid, Some(&clues));
+    assert_eq!(ipuz["clues"]["Across"][0]["clue"], "Clue for aper");
+
+    let html = render::render_html(&grid, "Proof", Some(&clues));
+    assert!(html.contains("<li value=\"1\">Clue for aper</li>"));
+
+    let puz_file = puz::read_puz(&puz::write_puz(&grid, "", Some(&clues)).unwrap()).unwrap();
+    assert_eq!(puz_file.clues.len(), entries.len());
+    assert!(puz_file.clues.iter().all(|x| x.starts_with("Clue for ")));
+}
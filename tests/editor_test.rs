@@ -0,0 +1,95 @@
+use puzzler::bigramindex::BigramIndexTree;
+use puzzler::editor::GridEditor;
+use puzzler::puzzlegrid::{Direction, PuzzleGrid};
+use puzzler::types::PairChar;
+use std::sync::atomic::AtomicBool;
+
+#[test]
+fn place_undo_redo() {
+    let word_store = puzzler::generate_wordstore("tests/words-good-4x4");
+    let index = BigramIndexTree::build(4, &word_store, 0);
+    let mut editor = GridEditor::new(4, 4, &index, &index).unwrap();
+
+    editor.place_word(Direction::Across, 0, "distrust").unwrap();
+    editor.place_word(Direction::Down, 0, "dimeride").unwrap();
+    assert!(editor.place_word(Direction::Down, 1, "stem").is_err());
+    assert!(editor.place_word(Direction::Down, 4, "stemless").is_err());
+    assert_eq!(editor.grid().to_text(), "di st ru st \nme ?? ?? ?? \nri ?? ?? ?? \nde ?? ?? ?? \n");
+
+    assert!(editor.undo());
+    assert_eq!(editor.get_tile(0, 1), PairChar::encode(b'?', b'?'));
+    assert!(editor.redo());
+    assert_eq!(editor.get_tile(0, 1), PairChar::encode(b'm', b'e'));
+    assert!(!editor.redo());
+
+    assert!(editor.undo());
+    assert!(editor.undo());
+    assert!(!editor.undo());
+    assert!(editor.is_open(Direction::Across, 0));
+}
+
+#[test]
+fn tiles_and_sizes() {
+    let word_store = puzzler::generate_wordstore("tests/words-good-4x4");
+    let index = BigramIndexTree::build(4, &word_store, 0);
+    assert!(GridEditor::new(0, 0, &index, &index).is_err());
+    assert!(GridEditor::new(4, 0, &index, &index).is_err());
+
+    let mut editor = GridEditor::new(4, 4, &index, &index).unwrap();
+    editor.set_tile(1, 2, PairChar::encode(b'n', b'g')).unwrap();
+    editor.set_tile(2, 2, PairChar::encode(b'_', b'_')).unwrap();
+    assert!(editor.set_tile(4, 0, PairChar::encode(b'n', b'g')).is_err());
+    assert_eq!(editor.grid().to_text(), "?? ?? ?? ?? \n?? ?? ?? ?? \n?? ng __ ?? \n?? ?? ?? ?? \n");
+
+    assert!(editor.undo());
+    assert!(editor.get_tile(2, 2).is_wildcard());
+}
+
+#[test]
+fn suggestions_and_dead_cells() {
+    let word_store = puzzler::generate_wordstore("tests/words-good-4x4");
+    let index = BigramIndexTree::build(4, &word_store, 0);
+    let mut editor = GridEditor::new(4, 4, &index, &index).unwrap();
+
+    editor.place_word(Direction::Across, 0, "distrust").unwrap();
+    let suggestions: Vec<String> = editor
+        .suggestions(Direction::Across, 1, 10)
+        .iter()
+        .map(|x| x.decode())
+        .collect();
+    assert_eq!(suggestions, vec!["meristem"]);
+    assert!(editor.dead_cells().is_empty());
+
+    editor.place_word(Direction::Across, 1, "ringable").unwrap();
+    assert!(editor.suggestions(Direction::Down, 0, 10).is_empty());
+    assert!(editor.dead_cells().contains(&(0, 1)));
+}
+
+#[test]
+fn autocomplete_keeps_placed_words() {
+    let word_store = puzzler::generate_wordstore("tests/words-good-4x4");
+    let index = BigramIndexTree::build(4, &word_store, 0);
+    let grid = PuzzleGrid::parse("?? ?? ?? ??\nme ri st em\n?? ?? ?? ??\n?? ?? ?? ??\n").unwrap();
+    let mut editor = GridEditor::from_grid(&grid, &index, &index).unwrap();
+
+    assert!(editor.autocomplete());
+    assert_eq!(
+        editor.grid().to_text(),
+        "di st ru st \nme ri st em \nri ng ab le \nde ed le ss \n"
+    );
+    assert!(editor.undo());
+    assert_eq!(editor.grid().to_text(), grid.to_text());
+}
+
+#[test]
+fn autocomplete_stops() {
+    let word_store = puzzler::generate_wordstore("tests/words-good-4x4");
+    let index = BigramIndexTree::build(4, &word_store, 0);
+    let grid = PuzzleGrid::parse("?? ?? ?? ??\nme ri st em\n?? ?? ?? ??\n?? ?? ?? ??\n").unwrap();
+    let mut editor = GridEditor::from_grid(&grid, &index, &index).unwrap();
+
+    // a solve stopped before it starts leaves the grid alone
+    assert!(!editor.autocomplete_until(&AtomicBool::new(false)));
+    assert_eq!(editor.grid().to_text(), grid.to_text());
+    assert!(!editor.undo());
+}
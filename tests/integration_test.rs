@@ -1,4 +1,10 @@
 mod runpuzzler;
+use puzzler::puzzlegrid::{BlankRules, BlankSymmetry, Direction, PuzzleGrid, Repeats};
+use puzzler::report::RunReport;
+use puzzler::wordstore::{LineMode, SingleTiles, Wrap};
+use puzzler::shape::Shape;
+use puzzler::template::Template;
+use puzzler::validate;
 
 #[test]
 fn four_by_four_solution() {
@@ -19,3 +25,297 @@ fn no_repeats_rows() {
 fn no_repeats_symmetric() {
     assert!(!runpuzzler::solve_puzzler("tests/words-symmetric-5x5", 5, 5, 0));
 }
+
+#[test]
+fn symmetric_square() {
+    let mut grid = PuzzleGrid::new(5, 5);
+    grid.set_repeats(Repeats::Symmetric).unwrap();
+    let solution = runpuzzler::solve_square_grid("tests/words-symmetric-5x5", &grid, 0).unwrap();
+    assert!(solution.is_symmetric());
+    assert!(!solution.breaks_repeats());
+
+    let mut grid = PuzzleGrid::new(5, 5);
+    grid.set_repeats(Repeats::Allowed).unwrap();
+    assert!(runpuzzler::solve_square_grid("tests/words-symmetric-5x5", &grid, 0).is_some());
+
+    assert!(PuzzleGrid::new(4, 5).set_repeats(Repeats::Symmetric).is_err());
+}
+
+#[test]
+fn seeded_solution() {
+    let grid = runpuzzler::solve_seeded_puzzler("tests/words-good-4x4", "tests/seed-partial-4x4", 0).unwrap();
+    let second_row: Vec<String> = (0..4).map(|x| grid.get_tile(x, 1).decode()).collect();
+
+    assert_eq!(second_row, vec!["st", "ri", "ng", "ed"]);
+}
+
+#[test]
+fn seed_grid_parsing() {
+    assert!(PuzzleGrid::parse("di st ?? __\nme ri st\n").is_err());
+    assert!(PuzzleGrid::parse("di s? ru st\n").is_err());
+
+    let grid = PuzzleGrid::parse("\ndi ?? ru __\n").unwrap();
+    assert_eq!((grid.width(), grid.depth()), (4, 1));
+    assert!(grid.get_tile(1, 0).is_wildcard());
+    assert!(grid.get_tile(3, 0).is_blank());
+}
+
+#[test]
+fn template_solution() {
+    let grid = runpuzzler::solve_template_puzzler("tests/words-good-spaces-4x4", "tests/template-4x4").unwrap();
+    let template = Template::parse(&std::fs::read_to_string("tests/template-4x4").unwrap()).unwrap();
+
+    for x in 0..4 {
+        for y in 0..4 {
+            assert_eq!(grid.get_tile(x, y).is_blank(), template.is_blank(x, y));
+        }
+    }
+}
+
+#[test]
+fn rotational_blank_symmetry() {
+    let mut grid = PuzzleGrid::new(4, 4);
+    grid.set_blank_symmetry(BlankSymmetry::Rotational);
+
+    let grid = runpuzzler::solve_square_grid("tests/words-symmetric-blanks-4x4", &grid, 1).unwrap();
+    for x in 0..4 {
+        for y in 0..4 {
+            assert_eq!(grid.get_tile(x, y).is_blank(), grid.get_tile(3 - x, 3 - y).is_blank());
+        }
+    }
+}
+
+#[test]
+fn mirrored_blank_symmetry() {
+    for symmetry in &[BlankSymmetry::Horizontal, BlankSymmetry::Vertical] {
+        let mut grid = PuzzleGrid::new(4, 4);
+        grid.set_blank_symmetry(*symmetry);
+
+        assert!(runpuzzler::solve_square_grid("tests/words-symmetric-blanks-4x4", &grid, 1).is_none());
+    }
+}
+
+#[test]
+fn total_blank_budget() {
+    for (max_total_blanks, expect_solution) in &[(5, false), (6, true)] {
+        let mut grid = PuzzleGrid::new(4, 4);
+        grid.set_blank_rules(BlankRules {
+            max_total_blanks: Some(*max_total_blanks),
+            ..BlankRules::default()
+        });
+
+        let solution = runpuzzler::solve_square_grid("tests/words-good-spaces-4x4", &grid, 2);
+        assert_eq!(solution.is_some(), *expect_solution);
+    }
+}
+
+#[test]
+fn no_adjacent_blanks() {
+    let mut grid = PuzzleGrid::new(4, 4);
+    grid.set_blank_rules(BlankRules {
+        no_adjacent_blanks: true,
+        no_blank_lines: true,
+        ..BlankRules::default()
+    });
+
+    assert!(runpuzzler::solve_square_grid("tests/words-good-spaces-4x4", &grid, 2).is_none());
+    assert!(runpuzzler::solve_square_grid("tests/words-symmetric-blanks-4x4", &grid, 1).is_some());
+}
+
+#[test]
+fn separated_line_solution() {
+    let dictionary = "tests/words-separated-4x4";
+    let words: Vec<String> = std::fs::read_to_string(dictionary).unwrap().lines().map(String::from).collect();
+
+    let mut grid = PuzzleGrid::new(4, 4);
+    grid.set_line_mode(LineMode::Separated(SingleTiles::Dictionary));
+    let solution = runpuzzler::solve_square_grid(dictionary, &grid, 1).unwrap();
+
+    // every run of tiles between the blanks is a word in its own right
+    for y in 0..4 {
+        let row: String = (0..4).map(|x| solution.get_tile(x, y).decode()).collect::<Vec<String>>().join(" ");
+        for word in row.split("__").map(|x| x.replace(' ', "")).filter(|x| !x.is_empty()) {
+            assert!(words.contains(&word), "{} is not in the dictionary", word);
+        }
+    }
+
+    let word_store = puzzler::generate_wordstore(dictionary);
+    let separated = LineMode::Separated(SingleTiles::Dictionary);
+    assert!(validate::validate_grid(&solution, &word_store, 1, separated, Wrap::Flat).is_empty());
+
+    grid.set_line_mode(LineMode::Joined);
+    assert!(runpuzzler::solve_square_grid(dictionary, &grid, 1).is_none());
+    grid.set_line_mode(LineMode::Separated(SingleTiles::Forbid));
+    assert!(runpuzzler::solve_square_grid(dictionary, &grid, 1).is_none());
+}
+
+#[test]
+fn shaped_solution() {
+    let grid = runpuzzler::solve_shape_puzzler("tests/words-shape-plus", "tests/shape-plus-4x4", 0).unwrap();
+
+    for (x, y) in &[(0, 0), (3, 0), (0, 3), (3, 3)] {
+        assert!(grid.get_tile(*x, *y).is_outside());
+    }
+    assert!(!grid.get_tile(1, 0).is_outside());
+
+    // the cells outside the shape are printed so that the grid can be read back in
+    let text = grid.to_text();
+    assert!(text.starts_with("## "));
+    assert_eq!(PuzzleGrid::parse(&text).unwrap().to_text(), text);
+
+    // and the grid read back in validates, a segment at a time
+    let word_store = puzzler::generate_wordstore("tests/words-shape-plus");
+    let parsed = PuzzleGrid::parse(&text).unwrap();
+    assert!(validate::validate_grid(&parsed, &word_store, 0, LineMode::Joined, Wrap::Flat).is_empty());
+}
+
+#[test]
+fn shape_parsing() {
+    let shape = Shape::parse("x.\n...\n").unwrap();
+    assert_eq!((shape.width(), shape.depth()), (3, 2));
+    assert!(!shape.is_inside(2, 0));
+
+    // the lone cell at the top right isn't part of a line either way
+    assert!(Shape::parse("..x.\n..xx\n").is_err());
+}
+
+#[test]
+fn toroidal_solution() {
+    let mut grid = PuzzleGrid::new(4, 4);
+    assert!(runpuzzler::solve_square_grid("tests/words-toroidal-4x4", &grid, 0).is_none());
+
+    grid.set_wrap(Wrap::AnyRotation);
+    let solution = runpuzzler::solve_square_grid("tests/words-toroidal-4x4", &grid, 0).unwrap();
+
+    let word_store = puzzler::generate_wordstore("tests/words-toroidal-4x4");
+    assert!(validate::validate_grid(&solution, &word_store, 0, LineMode::Joined, Wrap::AnyRotation).is_empty());
+    assert!(!validate::validate_grid(&solution, &word_store, 0, LineMode::Joined, Wrap::Flat).is_empty());
+}
+
+#[test]
+fn toroidal_rotations_are_duplicates() {
+    let mut grid = PuzzleGrid::parse("ab cd ef\ncd ef ab\nxx yy zz\n").unwrap();
+    assert!(!grid.has_duplicates());
+
+    grid.set_wrap(Wrap::AnyRotation);
+    assert!(grid.has_duplicates());
+}
+
+#[test]
+fn magic_square_diagonals() {
+    let mut grid = PuzzleGrid::new(4, 4);
+    grid.set_diagonals(true).unwrap();
+    let solution = runpuzzler::solve_square_grid("tests/words-magic-4x4", &grid, 0).unwrap();
+
+    // both diagonals of the solution have to be in the dictionary as well
+    let words = std::fs::read_to_string("tests/words-magic-4x4").unwrap();
+    let (leading, trailing) = solution.get_diagonal_stems();
+    for diagonal in [leading, trailing] {
+        let word: String = diagonal.iter().map(|x| x.decode()).collect();
+        assert!(words.lines().any(|x| x == word));
+    }
+
+    assert!(PuzzleGrid::new(4, 5).set_diagonals(true).is_err());
+}
+
+#[test]
+fn word_cube_solution() {
+    let dictionary = "tests/words-cube-3x3x3";
+    let words: Vec<String> = std::fs::read_to_string(dictionary).unwrap().lines().map(String::from).collect();
+
+    let cube = runpuzzler::solve_cube_puzzler(dictionary, 3, 3, 3).unwrap();
+    assert!(cube.is_complete());
+    assert!(!cube.has_duplicates());
+
+    // every pillar running up through the slices has to be a word too
+    for x in 0..3 {
+        for y in 0..3 {
+            let pillar: String = cube.get_pillar(x, y).iter().map(|x| x.decode()).collect();
+            assert!(words.contains(&pillar));
+        }
+    }
+
+    let json = RunReport::new(3, 3, 0).cube_to_json(3, Some(&cube));
+    assert_eq!(json["outcome"], "solved");
+    assert_eq!(json["slices"].as_array().unwrap().len(), 3);
+    assert_eq!(json["slices"][2][1][0], cube.get_tile(0, 1, 2).decode());
+}
+
+#[test]
+fn json_report() {
+    let grid = runpuzzler::solve_seeded_puzzler("tests/words-good-4x4", "tests/seed-partial-4x4", 0).unwrap();
+
+    let mut report = RunReport::new(4, 4, 0);
+    report.set_dictionary_fingerprint(puzzler::generate_wordstore("tests/words-good-4x4").fingerprint());
+    report.finish_stage("populate");
+    let json = report.to_json(Some(&grid));
+
+    assert_eq!(json["outcome"], "solved");
+    assert_eq!(json["grid"][1], serde_json::json!(["st", "ri", "ng", "ed"]));
+    assert_eq!(json["across"][1], "stringed");
+    assert_eq!(json["down"].as_array().unwrap().len(), 4);
+    assert_eq!(json["dictionary"].as_str().unwrap().len(), 16);
+    assert!(json["timings_ms"]["populate"].is_number());
+
+    assert_eq!(RunReport::new(4, 4, 0).to_json(None)["outcome"], "no_solution");
+}
+
+#[test]
+fn numbered_entries() {
+    let grid = PuzzleGrid::parse("ab cd __\nef gh ij\n__ kl mn\n").unwrap();
+    let entries = grid.entries();
+
+    let numbers: Vec<(usize, Direction)> = entries.iter().map(|x| (x.number, x.direction)).collect();
+    assert_eq!(
+        numbers,
+        vec![
+            (1, Direction::Across),
+            (1, Direction::Down),
+            (2, Direction::Down),
+            (3, Direction::Across),
+            (4, Direction::Down),
+            (5, Direction::Across),
+        ]
+    );
+
+    let down_four = &entries[4];
+    assert_eq!((down_four.x, down_four.y, down_four.length), (2, 1, 2));
+    assert_eq!(down_four.cells(), vec![(2, 1), (2, 2)]);
+    assert_eq!(down_four.to_string(), "4 down (2, 1) ijmn");
+
+    // a blank ends an entry however the solver reads the line
+    let mut grid = PuzzleGrid::parse("ab cd __ ef gh\n").unwrap();
+    let words = |grid: &PuzzleGrid| -> Vec<String> {
+        grid.entries().iter().filter(|x| x.direction == Direction::Across).map(|x| x.to_string()).collect()
+    };
+    assert_eq!(words(&grid), vec!["1 across (0, 0) abcd", "2 across (3, 0) efgh"]);
+    grid.set_line_mode(LineMode::Separated(SingleTiles::Dictionary));
+    assert_eq!(words(&grid), vec!["1 across (0, 0) abcd", "2 across (3, 0) efgh"]);
+}
+
+#[test]
+fn skip_lines_which_arent_utf8() {
+    let words = puzzler::ingest::read_even_words("tests/words-invalid-utf8");
+    assert_eq!(words, vec!["distrust", "meristem", "ringable"]);
+}
+
+#[test]
+fn entries_stop_at_blanks() {
+    let grid = PuzzleGrid::parse("ap er __ ea\nac ra ze __\n__ __ ta ve\nhe nt __ __\n").unwrap();
+    let entries: Vec<String> = grid.entries().iter().map(|x| x.to_string()).collect();
+
+    // the joined lines the solver placed are split into crossword entries at each blank, and
+    // tiles standing alone between blanks aren't entries
+    assert_eq!(
+        entries,
+        vec![
+            "1 across (0, 0) aper",
+            "1 down (0, 0) apac",
+            "2 down (1, 0) erra",
+            "3 across (0, 1) acraze",
+            "4 down (2, 1) zeta",
+            "5 across (2, 2) tave",
+            "6 across (0, 3) hent",
+        ]
+    );
+}
@@ -0,0 +1,80 @@
+use puzzler::ipuz;
+use puzzler::puzzlegrid::PuzzleGrid;
+use std::collections::HashMap;
+
+#[test]
+fn ipuz_round_trip() {
+    let grid = PuzzleGrid::parse("di st ru st\nme ri st em\nri ng ab le\nde ed le ss\n").unwrap();
+
+    let ipuz_text = ipuz::write_ipuz(&grid, None).to_string();
+    let read_grid = ipuz::read_ipuz(&ipuz_text).unwrap();
+
+    assert_eq!(read_grid.get_rows(), grid.get_rows());
+    assert!(read_grid.is_complete());
+}
+
+#[test]
+fn ipuz_blocks_and_numbers() {
+    let grid = PuzzleGrid::parse("ab cd __\nef gh ij\n__ kl mn\n").unwrap();
+
+    let mut clues: HashMap<String, String> = HashMap::new();
+    clues.insert("efghij".to_string(), "Middle row".to_string());
+    let ipuz = ipuz::write_ipuz(&grid, Some(&clues));
+
+    assert_eq!(ipuz["puzzle"][0], serde_json::json!([1, 2, "#"]));
+    assert_eq!(ipuz["puzzle"][1], serde_json::json!([3, 0, 4]));
+    assert_eq!(ipuz["solution"][2], serde_json::json!(["#", "KL", "MN"]));
+    assert_eq!(ipuz["clues"]["Across"][1]["clue"], "Middle row");
+    assert_eq!(ipuz["clues"]["Down"][2]["answer"], "IJMN");
+
+    // the blocks come back as blanks
+    let read_grid = ipuz::read_ipuz(&ipuz.to_string()).unwrap();
+    assert!(read_grid.get_tile(2, 0).is_blank());
+}
+
+#[test]
+fn ipuz_puzzle_as_seed() {
+    let ipuz_text = r##"{
+        "dimensions": {"width": 2, "height": 2},
+        "puzzle": [[1, 2], ["#", 0]]
+    }"##;
+    let grid = ipuz::read_ipuz(ipuz_text).unwrap();
+
+    assert!(grid.get_tile(0, 0).is_wildcard());
+    assert!(grid.get_tile(0, 1).is_blank());
+    assert!(ipuz::read_ipuz("{}").is_err());
+}
+
+#[test]
+fn ipuz_numbers_stop_at_blocks() {
+    // joined lines run through their blanks, but the numbers follow the blocks
+    let grid = PuzzleGrid::parse("ap er __ ea\nac ra ze __\n__ __ ta ve\nhe nt __ __\n").unwrap();
+    let ipuz = ipuz::write_ipuz(&grid, None);
+
+    assert_eq!(ipuz["puzzle"][0], serde_json::json!([1, 2, "#", 0]));
+    assert_eq!(ipuz["puzzle"][1], serde_json::json!([3, 0, 4, "#"]));
+    assert_eq!(ipuz["puzzle"][2], serde_json::json!(["#", "#", 5, 0]));
+    assert_eq!(ipuz["puzzle"][3], serde_json::json!([6, 0, "#", "#"]));
+}
+
+#[test]
+fn ipuz_missing_cells() {
+    let short_row = r##"{
+        "dimensions": {"width": 2, "height": 2},
+        "puzzle": [[1, 2], ["#"]]
+    }"##;
+    assert!(ipuz::read_ipuz(short_row).is_err());
+
+    let missing_row = r##"{
+        "dimensions": {"width": 2, "height": 2},
+        "puzzle": [[1, 2]]
+    }"##;
+    assert!(ipuz::read_ipuz(missing_row).is_err());
+
+    // null cells are outside the grid rather than missing
+    let omitted = r##"{
+        "dimensions": {"width": 2, "height": 2},
+        "puzzle": [[1, 2], [null, 0]]
+    }"##;
+    assert!(ipuz::read_ipuz(omitted).unwrap().get_tile(0, 1).is_outside());
+}
@@ -0,0 +1,52 @@
+use puzzler::puz;
+use puzzler::puzzlegrid::PuzzleGrid;
+use std::collections::HashMap;
+
+#[test]
+fn puz_round_trip() {
+    let grid = PuzzleGrid::parse("ab cd __\nef gh ij\n__ kl mn\n").unwrap();
+
+    let mut clues: HashMap<String, String> = HashMap::new();
+    clues.insert("efghij".to_string(), "Middle row".to_string());
+    clues.insert("ijmn".to_string(), "Right column".to_string());
+    let bytes = puz::write_puz(&grid, "Test", Some(&clues)).unwrap();
+
+    let puz_file = puz::read_puz(&bytes).unwrap();
+    assert_eq!(puz_file.grid.get_rows(), grid.get_rows());
+    assert!(puz_file.grid.get_tile(2, 0).is_blank());
+    assert_eq!(puz_file.title, "Test");
+
+    // clues run by number, with across before down: 1a 1d 2d 3a 4d 5a
+    assert_eq!(puz_file.clues, vec!["", "", "", "Middle row", "Right column", ""]);
+}
+
+#[test]
+fn puz_checksums() {
+    let grid = PuzzleGrid::parse("di st ru st\nme ri st em\nri ng ab le\nde ed le ss\n").unwrap();
+    let mut bytes = puz::write_puz(&grid, "", None).unwrap();
+
+    assert_eq!(&bytes[0x02..0x0E], b"ACROSS&DOWN\0");
+    assert_eq!(bytes[0x2E], 8);
+    assert!(puz::read_puz(&bytes).is_ok());
+
+    // changing a letter breaks the solution checksum
+    bytes[0x34] = b'Z';
+    assert!(puz::read_puz(&bytes).is_err());
+
+    assert!(puz::write_puz(&PuzzleGrid::parse("ab ??\ncd ef\n").unwrap(), "", None).is_err());
+}
+
+#[test]
+fn puz_clues_stop_at_blocks() {
+    // a joined solve reads the top row as one word, but its blank ends the first entry
+    let grid = PuzzleGrid::parse("ap er __ ea\nac ra ze __\n__ __ ta ve\nhe nt __ __\n").unwrap();
+
+    let mut clues: HashMap<String, String> = HashMap::new();
+    clues.insert("aper".to_string(), "Top left".to_string());
+    clues.insert("tave".to_string(), "Third row".to_string());
+    let puz_file = puz::read_puz(&puz::write_puz(&grid, "", Some(&clues)).unwrap()).unwrap();
+
+    // 1a 1d 2d 3a 4d 5a 6a, the same numbering as the entries
+    assert_eq!(puz_file.clues.len(), grid.entries().len());
+    assert_eq!(puz_file.clues, vec!["Top left", "", "", "", "", "Third row", ""]);
+}
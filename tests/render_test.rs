@@ -0,0 +1,29 @@
+use puzzler::puzzlegrid::PuzzleGrid;
+use puzzler::render;
+
+#[test]
+fn svg_cells() {
+    let grid = PuzzleGrid::parse("ab cd __\nef gh ij\n__ kl mn\n").unwrap();
+
+    let svg = render::render_svg(&grid, true, true);
+    assert!(svg.starts_with("<svg"));
+    assert_eq!(svg.matches("<rect class=\"letter\"").count(), 7);
+    assert_eq!(svg.matches("<rect class=\"blank\"").count(), 2);
+    assert_eq!(svg.matches("class=\"number\"").count(), 5);
+    assert!(svg.contains(">GH</text>"));
+
+    // the solving view leaves the letter cells empty
+    let svg = render::render_svg(&grid, false, false);
+    assert!(!svg.contains(">GH</text>"));
+    assert!(!svg.contains("class=\"number\""));
+}
+
+#[test]
+fn html_views() {
+    let grid = PuzzleGrid::parse("ab cd\nef gh\n").unwrap();
+
+    let html = render::render_html(&grid, "Proof <1>", None);
+    assert!(html.contains("<title>Proof &lt;1&gt;</title>"));
+    assert_eq!(html.matches("<svg").count(), 2);
+    assert_eq!(html.matches(">AB</text>").count(), 1);
+}
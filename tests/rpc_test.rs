@@ -0,0 +1,204 @@
+mod slowwords;
+use puzzler::rpc;
+use puzzler::service::Service;
+use serde_json::{json, Value};
+use std::io::{BufReader, Read, Write};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// collects what the rpc writes, so it can be read back once the input has run out
+#[derive(Clone)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SharedBuffer {
+    fn messages(&self) -> Vec<Value> {
+        let output = String::from_utf8(self.0.lock().unwrap().clone()).unwrap();
+        output.lines().map(|x| serde_json::from_str(x).unwrap()).collect()
+    }
+
+    // wait for a message to turn up, failing the test if it takes too long
+    fn wait_for(&self, matches: impl Fn(&Value) -> bool) -> Value {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(30) {
+            if let Some(v) = self.messages().into_iter().find(|x| matches(x)) {
+                return v;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("Gave up waiting for a message, got {:?}", self.messages());
+    }
+}
+
+// input which stays open until the sender is dropped, so a job can run while the test waits
+struct ChannelInput {
+    receiver: Receiver<String>,
+    pending: Vec<u8>,
+}
+
+impl Read for ChannelInput {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pending.is_empty() {
+            match self.receiver.recv() {
+                Ok(v) => self.pending = format!("{}\n", v).into_bytes(),
+                Err(_) => return Ok(0),
+            }
+        }
+        let length = buf.len().min(self.pending.len());
+        buf[..length].copy_from_slice(&self.pending[..length]);
+        self.pending.drain(..length);
+        Ok(length)
+    }
+}
+
+// an rpc session on its own thread, fed requests one at a time
+fn start_session(dictionary: &str) -> (Sender<String>, SharedBuffer, thread::JoinHandle<()>) {
+    let (sender, receiver) = mpsc::channel();
+    let input = BufReader::new(ChannelInput {
+        receiver,
+        pending: Vec::new(),
+    });
+    let buffer = SharedBuffer(Arc::new(Mutex::new(Vec::new())));
+    let output = buffer.clone();
+    let service = Arc::new(Service::new(dictionary));
+    let session = thread::spawn(move || rpc::run_rpc(service, input, output));
+    (sender, buffer, session)
+}
+
+fn run_requests(requests: &[Value]) -> Vec<Value> {
+    let input: String = requests.iter().map(|x| format!("{}\n", x)).collect();
+    let buffer = SharedBuffer(Arc::new(Mutex::new(Vec::new())));
+    rpc::run_rpc(
+        Arc::new(Service::new("tests/words-good-4x4")),
+        input.as_bytes(),
+        buffer.clone(),
+    );
+
+    let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    output.lines().map(|x| serde_json::from_str(x).unwrap()).collect()
+}
+
+#[test]
+fn requests_and_errors() {
+    let responses = run_requests(&[
+        json!({ "id": 1, "method": "build_index", "params": { "size": 4 } }),
+        json!({ "id": 2, "method": "candidates", "params": { "pattern": "?? ?? ?? st" } }),
+        json!({ "id": 3, "method": "validate", "params": { "grid": "di st ru st\nme ri st em\n" } }),
+        json!({ "id": 4, "method": "cancel", "params": { "job": 7 } }),
+        json!({ "id": 5, "method": "unknown" }),
+    ]);
+
+    // the index is built on a worker, so its response can come after the others
+    assert_eq!(responses.len(), 5);
+    let response = |id: u64| responses.iter().find(|x| x["id"] == id).unwrap().clone();
+    assert_eq!(response(1)["result"]["size"], 4);
+    assert_eq!(response(2), json!({ "id": 2, "result": { "candidates": ["distrust"] } }));
+    assert_eq!(response(3)["result"]["valid"], false);
+    assert_eq!(response(4), json!({ "id": 4, "error": "No such job 7" }));
+    assert!(response(5)["error"].is_string());
+}
+
+#[test]
+fn bad_requests_keep_the_session() {
+    let (sender, buffer, session) = start_session("tests/words-good-4x4");
+    let pattern = vec!["??"; 13].join(" ");
+    for request in [
+        json!({ "id": 1, "method": "build_index", "params": { "size": 0 } }),
+        json!({ "id": 2, "method": "candidates", "params": { "pattern": pattern } }),
+        json!({ "id": 3, "method": "candidates", "params": { "pattern": "?? ?? ?? st" } }),
+    ] {
+        sender.send(request.to_string()).unwrap();
+    }
+
+    assert!(buffer.wait_for(|x| x["id"] == 1)["error"].is_string());
+    assert!(buffer.wait_for(|x| x["id"] == 2)["error"].is_string());
+    assert_eq!(buffer.wait_for(|x| x["id"] == 3)["result"]["candidates"], json!(["distrust"]));
+    drop(sender);
+    session.join().unwrap();
+}
+
+#[test]
+fn solve_notifications() {
+    let (sender, buffer, session) = start_session("tests/words-good-4x4");
+    let request = json!({
+        "id": 1,
+        "method": "solve",
+        "params": { "seed": "?? ?? ?? ??\nme ri st em\n?? ?? ?? ??\n?? ?? ?? ??\n" },
+    });
+    sender.send(request.to_string()).unwrap();
+
+    let finished = buffer.wait_for(|x| x["method"] == "finished");
+    assert_eq!(finished["params"]["status"], "solved");
+    assert_eq!(finished["params"]["across"], json!(["distrust", "meristem", "ringable", "deedless"]));
+    drop(sender);
+    session.join().unwrap();
+
+    let messages = buffer.messages();
+    assert_eq!(messages[0]["id"], 1);
+    assert_eq!(messages[0]["result"]["job"], 1);
+    assert!(messages[1..(messages.len() - 1)].iter().all(|x| x["method"] == "progress"));
+}
+
+#[test]
+fn cancel_running_solve() {
+    let (sender, buffer, session) = start_session(slowwords::slow_dictionary());
+    sender
+        .send(json!({ "id": 1, "method": "solve", "params": { "width": 8, "depth": 8 } }).to_string())
+        .unwrap();
+    let progress = buffer.wait_for(|x| x["method"] == "progress");
+    assert_eq!(progress["params"]["status"], "running");
+
+    sender.send(json!({ "id": 2, "method": "cancel", "params": { "job": 1 } }).to_string()).unwrap();
+    assert_eq!(buffer.wait_for(|x| x["id"] == 2)["result"]["status"], "cancelled");
+    let finished = buffer.wait_for(|x| x["method"] == "finished");
+    assert_eq!(finished["params"]["status"], "cancelled");
+
+    drop(sender);
+    session.join().unwrap();
+}
+
+// an output which takes one line and then fails, as stdout does once the editor has gone
+struct ClosingOutput {
+    lines_left: usize,
+}
+
+impl Write for ClosingOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.lines_left == 0 {
+            return Err(std::io::ErrorKind::BrokenPipe.into());
+        }
+        self.lines_left -= buf.iter().filter(|x| **x == b'\n').count().min(self.lines_left);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn closed_output_ends_session() {
+    let requests = [
+        json!({ "id": 1, "method": "solve", "params": { "width": 4, "depth": 4 } }),
+        json!({ "id": 2, "method": "build_index", "params": { "size": 4 } }),
+    ];
+    let input: String = requests.iter().map(|x| format!("{}\n", x)).collect();
+
+    // the job's watcher fails to send its notifications, which mustn't bring the session down
+    rpc::run_rpc(
+        Arc::new(Service::new("tests/words-good-4x4")),
+        input.as_bytes(),
+        ClosingOutput { lines_left: 1 },
+    );
+}
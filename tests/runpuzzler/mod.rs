@@ -1,5 +1,9 @@
-use puzzler;
-use puzzler::bigramindex::BigramIndexTree;
+use puzzler::bigramindex::{BigramIndexTree, IndexCache};
+use puzzler::puzzlecube::{CubeIndices, PuzzleCube};
+use puzzler::puzzlegrid::PuzzleGrid;
+use puzzler::shape::{Shape, ShapeSolver};
+use puzzler::template::Template;
+use puzzler::wordstore::{LineMode, WordStore};
 
 pub fn solve_puzzler(dictionary_file: &str, puzzle_width: usize, puzzle_depth: usize, spaces: usize) -> bool {
     let word_store = puzzler::generate_wordstore(dictionary_file);
@@ -15,3 +19,70 @@ pub fn solve_puzzler(dictionary_file: &str, puzzle_width: usize, puzzle_depth: u
         None => false,
     }
 }
+
+pub fn solve_seeded_puzzler(dictionary_file: &str, seed_file: &str, spaces: usize) -> Option<PuzzleGrid> {
+    let seed_grid = PuzzleGrid::parse(&std::fs::read_to_string(seed_file).unwrap()).unwrap();
+
+    solve_square_grid(dictionary_file, &seed_grid, spaces)
+}
+
+// fill out a square grid which has already been set up with any constraints
+pub fn solve_square_grid(dictionary_file: &str, seed_grid: &PuzzleGrid, spaces: usize) -> Option<PuzzleGrid> {
+    let word_store = puzzler::generate_wordstore(dictionary_file);
+    assert_eq!(seed_grid.width(), seed_grid.depth());
+
+    let lines = word_store.lines_by_length(seed_grid.width(), spaces, seed_grid.line_mode());
+    let index = BigramIndexTree::build_from_words(seed_grid.width(), WordStore::wrap_lines(lines, seed_grid.wrap()));
+
+    let top_start_words = puzzler::generate_seeded_top_words(seed_grid, &index, &index);
+
+    puzzler::populate_seeded_grid(seed_grid, &top_start_words, &index, &index)
+}
+
+pub fn solve_template_puzzler(dictionary_file: &str, template_file: &str) -> Option<PuzzleGrid> {
+    let word_store = puzzler::generate_wordstore(dictionary_file);
+
+    let template = Template::parse(&std::fs::read_to_string(template_file).unwrap()).unwrap();
+    let mut grid = PuzzleGrid::new(template.width(), template.depth());
+    grid.apply_template(&template).unwrap();
+
+    let horizontal_index =
+        BigramIndexTree::build_for_patterns(template.width(), &word_store, &template.row_patterns(), LineMode::Joined);
+    let vertical_index =
+        BigramIndexTree::build_for_patterns(template.depth(), &word_store, &template.column_patterns(), LineMode::Joined);
+
+    let top_start_words = puzzler::generate_seeded_top_words(&grid, &horizontal_index, &vertical_index);
+
+    puzzler::populate_seeded_grid(&grid, &top_start_words, &horizontal_index, &vertical_index)
+}
+
+pub fn solve_shape_puzzler(dictionary_file: &str, shape_file: &str, spaces: usize) -> Option<PuzzleGrid> {
+    let word_store = puzzler::generate_wordstore(dictionary_file);
+
+    let shape = Shape::parse(&std::fs::read_to_string(shape_file).unwrap()).unwrap();
+    let mut index_cache = IndexCache::new(spaces, LineMode::Joined);
+    for length in shape.line_lengths() {
+        index_cache.get_or_build(length, &word_store);
+    }
+
+    let solver = ShapeSolver::new(&shape, &index_cache).unwrap();
+
+    puzzler::populate_shape(&solver)
+}
+
+pub fn solve_cube_puzzler(dictionary_file: &str, width: usize, depth: usize, height: usize) -> Option<PuzzleCube> {
+    let word_store = puzzler::generate_wordstore(dictionary_file);
+
+    let mut index_cache = IndexCache::new(0, LineMode::Joined);
+    for length in [width, depth, height] {
+        index_cache.get_or_build(length, &word_store);
+    }
+
+    let indices = CubeIndices {
+        horizontal: index_cache.get(width).unwrap(),
+        vertical: index_cache.get(depth).unwrap(),
+        pillar: index_cache.get(height).unwrap(),
+    };
+
+    puzzler::populate_cube(width, depth, height, indices)
+}
@@ -0,0 +1,132 @@
+mod slowwords;
+use puzzler::server;
+use puzzler::service::Service;
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+// poll a job until it's no longer running
+fn wait_for_job(service: &Service, job_id: u64) -> Value {
+    loop {
+        let status = service.job_status(job_id).unwrap();
+        if status["status"] != "running" {
+            return status;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn candidates_and_validate() {
+    let service = Service::new("tests/words-good-4x4");
+
+    let candidates = service.candidates(&json!({ "pattern": "?? ?? ?? st" })).unwrap();
+    assert_eq!(candidates["candidates"], json!(["distrust"]));
+    assert!(service.candidates(&json!({ "pattern": "d? ??" })).is_err());
+
+    let valid = service
+        .validate(&json!({ "grid": "di st ru st\nme ri st em\nri ng ab le\nde ed le ss\n" }))
+        .unwrap();
+    assert_eq!(valid, json!({ "valid": true, "errors": [] }));
+    assert!(service.validate(&json!({ "grid": "di", "dictionary": "tests/missing" })).is_err());
+}
+
+#[test]
+fn solve_jobs() {
+    let service = Service::new("tests/words-good-4x4");
+
+    let job = service
+        .start_solve(&json!({ "seed": "?? ?? ?? ??\nme ri st em\n?? ?? ?? ??\n?? ?? ?? ??\n" }))
+        .unwrap();
+    let status = wait_for_job(&service, job["job"].as_u64().unwrap());
+    assert_eq!(status["status"], "solved");
+    assert_eq!(status["across"], json!(["distrust", "meristem", "ringable", "deedless"]));
+
+    // a finished job can't be cancelled
+    let status = service.cancel_job(job["job"].as_u64().unwrap()).unwrap();
+    assert_eq!(status["status"], "solved");
+
+    let job = service.start_solve(&json!({ "seed": "di st ru st\n?? ?? ?? ??\nde ?? ?? ??\n" })).unwrap();
+    assert_eq!(wait_for_job(&service, job["job"].as_u64().unwrap())["status"], "no_solution");
+    assert!(service.job_status(99).is_none());
+}
+
+#[test]
+fn http_requests() {
+    let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || server::serve(listener, Arc::new(Service::new("tests/words-good-4x4"))));
+
+    let request = |text: String| -> String {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        stream.write_all(text.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+
+    let body = json!({ "pattern": "di ?? ?? st" }).to_string();
+    let response = request(format!(
+        "POST /candidates HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+        body.len(),
+        body
+    ));
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with(r#"{"candidates":["distrust"]}"#));
+
+    let response = request("GET /jobs/1 HTTP/1.1\r\nHost: localhost\r\n\r\n".to_string());
+    assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+    let response = request("POST /solve HTTP/1.1\r\nHost: localhost\r\nContent-Length: 99999999999\r\n\r\n".to_string());
+    assert!(response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+}
+
+#[test]
+fn cancel_and_time_out() {
+    let service = Service::new(slowwords::slow_dictionary());
+
+    // the indices are built on the job's thread, so the job starts out running
+    let job = service.start_solve(&json!({ "width": 8, "depth": 8 })).unwrap();
+    assert_eq!(job["status"], "running");
+    let job_id = job["job"].as_u64().unwrap();
+    thread::sleep(Duration::from_millis(500));
+    assert_eq!(service.job_status(job_id).unwrap()["status"], "running");
+
+    assert_eq!(service.cancel_job(job_id).unwrap()["status"], "cancelled");
+    assert_eq!(wait_for_job(&service, job_id)["status"], "cancelled");
+
+    let job = service.start_solve(&json!({ "width": 8, "depth": 8, "timeout_ms": 300 })).unwrap();
+    assert_eq!(wait_for_job(&service, job["job"].as_u64().unwrap())["status"], "timed_out");
+
+    assert!(service.start_solve(&json!({ "width": 8, "depth": 8, "dictionary": "tests/missing" })).is_err());
+}
+
+#[test]
+fn finished_jobs_expire() {
+    let mut service = Service::new(slowwords::slow_dictionary());
+    service.set_job_ttl(Duration::ZERO);
+
+    let job_id = service.start_solve(&json!({ "width": 8, "depth": 8 })).unwrap()["job"].as_u64().unwrap();
+    assert_eq!(service.job_status(job_id).unwrap()["status"], "running");
+    assert_eq!(service.cancel_job(job_id).unwrap()["status"], "cancelled");
+    assert!(service.job_status(job_id).is_none());
+}
+
+#[test]
+fn line_sizes_out_of_range() {
+    let service = Service::new("tests/words-good-4x4");
+
+    assert!(service.build_index(&json!({ "size": 0 })).is_err());
+    assert!(service.build_index(&json!({ "size": 13 })).is_err());
+    let pattern = vec!["??"; 13].join(" ");
+    assert!(service.candidates(&json!({ "pattern": pattern })).is_err());
+
+    // the indices for a solve are built on its thread, so a bad size fails the job
+    let job = service.start_solve(&json!({ "width": 13, "depth": 4 })).unwrap();
+    let status = wait_for_job(&service, job["job"].as_u64().unwrap());
+    assert_eq!(status["status"], "failed");
+    assert!(status["error"].as_str().unwrap().contains("13"));
+}
@@ -0,0 +1,23 @@
+use std::sync::OnceLock;
+
+// a dictionary of random 16 letter words, too many for the solver to rule out an 8x8 grid
+// quickly and too few for it to find one, written out once for the tests which need a solve
+// that keeps running
+pub fn slow_dictionary() -> &'static str {
+    static DICTIONARY: OnceLock<String> = OnceLock::new();
+    DICTIONARY.get_or_init(|| {
+        let mut seed: u64 = 1;
+        let mut words = String::new();
+        for _ in 0..20000 {
+            for _ in 0..16 {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                words.push((b'a' + (seed >> 33) as u8 % 4) as char);
+            }
+            words.push('\n');
+        }
+
+        let file = std::env::temp_dir().join(format!("puzzler-slow-words-{}", std::process::id()));
+        std::fs::write(&file, words).unwrap();
+        file.to_str().unwrap().to_string()
+    })
+}
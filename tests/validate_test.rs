@@ -0,0 +1,55 @@
+use puzzler::puzzlegrid::{Direction, PuzzleGrid};
+use puzzler::validate::{self, LineError};
+use puzzler::wordstore::{LineMode, SingleTiles, Wrap};
+
+#[test]
+fn valid_grid() {
+    let word_store = puzzler::generate_wordstore("tests/words-good-4x4");
+    let grid = PuzzleGrid::parse("di st ru st\nme ri st em\nri ng ab le\nde ed le ss\n").unwrap();
+
+    assert!(validate::validate_grid(&grid, &word_store, 0, LineMode::Joined, Wrap::Flat).is_empty());
+}
+
+#[test]
+fn invalid_and_duplicate_lines() {
+    let word_store = puzzler::generate_wordstore("tests/words-good-4x4");
+    let grid = PuzzleGrid::parse("di st ru st\nme ri st em\nri ng ab le\ndi st ru st\n").unwrap();
+
+    let invalid_lines = validate::validate_grid(&grid, &word_store, 0, LineMode::Joined, Wrap::Flat);
+    assert_eq!(invalid_lines.len(), 5);
+    assert_eq!(invalid_lines[0].direction, Direction::Across);
+    assert_eq!(invalid_lines[0].index, 3);
+    assert_eq!(invalid_lines[0].error, LineError::Duplicate(Direction::Across, 0));
+    assert_eq!(invalid_lines[1].to_string(), "column 1 'dimeridi' isn't a word");
+}
+
+#[test]
+fn blanks_as_solved() {
+    let word_store = puzzler::generate_wordstore("tests/words-good-spaces-4x4");
+    let grid = PuzzleGrid::parse("ap er __ ea\nac ra ze __\n__ __ ta ve\nhe nt __ __\n").unwrap();
+
+    assert!(validate::validate_grid(&grid, &word_store, 2, LineMode::Joined, Wrap::Flat).is_empty());
+
+    // the two blank rows and columns need max_blanks of 2
+    let invalid_lines = validate::validate_grid(&grid, &word_store, 1, LineMode::Joined, Wrap::Flat);
+    assert_eq!(invalid_lines.len(), 4);
+    assert!(invalid_lines.iter().all(|x| x.error == LineError::NotAWord));
+
+    let grid = PuzzleGrid::parse("ap er ?? ea\nac ra ze __\n__ __ ta ve\nhe nt __ __\n").unwrap();
+    assert_eq!(validate::validate_grid(&grid, &word_store, 2, LineMode::Joined, Wrap::Flat)[0].error, LineError::Incomplete);
+}
+
+#[test]
+fn separated_rotations_repeat_on_a_toroidal_grid() {
+    let word_store = puzzler::generate_wordstore("tests/words-good-4x4");
+    let mut grid = PuzzleGrid::parse("ab cd __ __\n__ __ cd ab\n").unwrap();
+    let line_mode = LineMode::Separated(SingleTiles::Dictionary);
+    grid.set_line_mode(line_mode);
+    assert!(!grid.has_duplicates());
+
+    // abcd and cdab are the same word once the lines wrap around
+    grid.set_wrap(Wrap::AnyRotation);
+    assert!(grid.has_duplicates());
+    let invalid_lines = validate::validate_grid(&grid, &word_store, 2, line_mode, Wrap::AnyRotation);
+    assert!(invalid_lines.iter().any(|x| x.error == LineError::Duplicate(Direction::Across, 0)));
+}